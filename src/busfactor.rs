@@ -0,0 +1,366 @@
+use anyhow::{Result, anyhow};
+use git2::{ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::cli::Format;
+use crate::copies::CopyDetection;
+use crate::git::{ColorScheme, Target, TargetPath, blame_at};
+
+const SECONDS_PER_MONTH: i64 = 30 * 24 * 60 * 60;
+
+/// Knowledge concentration of one subdirectory
+#[derive(Serialize)]
+struct DirectoryReport {
+    path: String,
+    lines: usize,
+    /// The fewest authors who together own more than half of the lines
+    bus_factor: usize,
+    /// Those authors, largest share first
+    owners: Vec<OwnerReport>,
+    /// Main owners without any commit in the inactivity window
+    inactive_owners: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct OwnerReport {
+    name: String,
+    email: String,
+    lines: usize,
+    /// Percentage of the directory's lines
+    share: f64,
+    /// Date of the owner's last commit anywhere in the repository (YYYY-MM-DD)
+    last_commit: String,
+}
+
+/// Surviving lines per author in one subdirectory
+#[derive(Default)]
+struct Ownership {
+    lines: usize,
+    authors: HashMap<String, (String, String, usize)>,
+}
+
+/// Report, per subdirectory of `path`, how many authors own the majority of its lines
+/// Lines are attributed with blame; main owners without commits in `months` months are flagged
+pub fn get_bus_factor(
+    path: &TargetPath,
+    months: u32,
+    format: Format,
+    no_color: bool,
+) -> Result<String> {
+    if !matches!(format, Format::Text | Format::Json) {
+        return Err(anyhow!(
+            "wer bus-factor only supports --format text and json"
+        ));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let reports = bus_factor_reports(path, now - i64::from(months) * SECONDS_PER_MONTH)?;
+
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(&reports)?),
+        _ => Ok(format_table(path, &reports, months, no_color)),
+    }
+}
+
+fn bus_factor_reports(path: &TargetPath, inactive_before: i64) -> Result<Vec<DirectoryReport>> {
+    let target = Target::open(path, false)?;
+    let repo = &target.repo;
+    let commit = target.start_commit()?;
+
+    let root = if target.relative_path.as_os_str().is_empty() {
+        commit.tree()?
+    } else {
+        let entry = commit
+            .tree()?
+            .get_path(&target.relative_path)
+            .map_err(|_| anyhow!("'{}' is not tracked by git", path))?;
+        if entry.kind() != Some(ObjectType::Tree) {
+            return Err(anyhow!(
+                "Bus factor needs a directory, not a file: {}",
+                path
+            ));
+        }
+        entry.to_object(repo)?.peel_to_tree()?
+    };
+
+    // Files grouped by their subdirectory, "" for files directly in the directory
+    let mut groups: BTreeMap<String, Vec<(String, Oid)>> = BTreeMap::new();
+    root.walk(TreeWalkMode::PreOrder, |parent, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            let group = parent.split('/').next().unwrap_or("").to_string();
+            let name = format!("{}{}", parent, entry.name().unwrap_or(""));
+            groups.entry(group).or_default().push((name, entry.id()));
+        }
+        TreeWalkResult::Ok
+    })?;
+
+    let last_commits = last_commit_times(repo, commit.id())?;
+
+    let mut reports = Vec::new();
+    for (group, files) in groups {
+        let mut ownership = Ownership::default();
+        for (name, blob_id) in files {
+            if repo.find_blob(blob_id)?.is_binary() {
+                continue;
+            }
+
+            let file_path = target.relative_path.join(&name);
+            let blame = blame_at(
+                repo,
+                &file_path,
+                Some(commit.id()),
+                CopyDetection::default(),
+            )?;
+            for hunk in blame.iter() {
+                let signature = hunk.final_signature();
+                let email = signature.email().unwrap_or("").to_string();
+                let lines = hunk.lines_in_hunk();
+                ownership.lines += lines;
+                ownership
+                    .authors
+                    .entry(email.to_lowercase())
+                    .or_insert_with(|| {
+                        (signature.name().unwrap_or("Unknown").to_string(), email, 0)
+                    })
+                    .2 += lines;
+            }
+        }
+
+        if ownership.lines == 0 {
+            continue;
+        }
+        let label = group_label(&target.relative_path, &group);
+        reports.push(directory_report(
+            label,
+            ownership,
+            &last_commits,
+            inactive_before,
+        ));
+    }
+
+    reports.sort_by(|a, b| {
+        a.bus_factor
+            .cmp(&b.bus_factor)
+            .then(b.lines.cmp(&a.lines))
+            .then(a.path.cmp(&b.path))
+    });
+
+    Ok(reports)
+}
+
+/// Pick the fewest authors owning more than half the lines, biggest owners first
+fn directory_report(
+    path: String,
+    ownership: Ownership,
+    last_commits: &HashMap<String, i64>,
+    inactive_before: i64,
+) -> DirectoryReport {
+    let mut authors: Vec<(String, (String, String, usize))> =
+        ownership.authors.into_iter().collect();
+    authors.sort_by(|a, b| b.1.2.cmp(&a.1.2).then(a.1.0.cmp(&b.1.0)));
+
+    let mut owners = Vec::new();
+    let mut inactive_owners = Vec::new();
+    let mut owned = 0;
+    for (key, (name, email, lines)) in authors {
+        if owned * 2 > ownership.lines {
+            break;
+        }
+        owned += lines;
+
+        let last_commit = last_commits.get(&key).copied().unwrap_or(0);
+        if last_commit < inactive_before {
+            inactive_owners.push(name.clone());
+        }
+        owners.push(OwnerReport {
+            name,
+            email,
+            lines,
+            share: (lines as f64 * 1000.0 / ownership.lines as f64).round() / 10.0,
+            last_commit: chrono::DateTime::from_timestamp(last_commit, 0)
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+        });
+    }
+
+    DirectoryReport {
+        path,
+        lines: ownership.lines,
+        bus_factor: owners.len(),
+        owners,
+        inactive_owners,
+    }
+}
+
+/// Display path of a group: "dir/sub/" for subdirectories, "dir/ (files)" for files directly in it
+fn group_label(directory: &Path, group: &str) -> String {
+    let prefix = if directory.as_os_str().is_empty() {
+        String::new()
+    } else {
+        format!("{}/", directory.display())
+    };
+
+    if group.is_empty() {
+        let directory = if prefix.is_empty() { "./" } else { &prefix };
+        format!("{} (files)", directory)
+    } else {
+        format!("{}{}/", prefix, group)
+    }
+}
+
+/// Time of each author's most recent commit reachable from `start`, keyed by lowercase email
+fn last_commit_times(repo: &Repository, start: Oid) -> Result<HashMap<String, i64>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(start)?;
+
+    let mut last_commits = HashMap::new();
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        let email = commit.author().email().unwrap_or("").to_lowercase();
+        let time = commit.time().seconds();
+        last_commits
+            .entry(email)
+            .and_modify(|last: &mut i64| *last = (*last).max(time))
+            .or_insert(time);
+    }
+
+    Ok(last_commits)
+}
+
+fn format_table(
+    path: &TargetPath,
+    reports: &[DirectoryReport],
+    months: u32,
+    no_color: bool,
+) -> String {
+    let colors = ColorScheme::new(no_color);
+    let mut result = format!(
+        "Bus factor for {}: fewest authors owning more than half of the lines\n",
+        path
+    );
+
+    let width = reports
+        .iter()
+        .map(|report| report.path.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Directory".len());
+    result.push_str(&format!(
+        "\n{:<width$}  {:>7}  {:>10}  Main owners",
+        "Directory",
+        "Lines",
+        "Bus factor",
+        width = width
+    ));
+
+    for report in reports {
+        let owners: Vec<String> = report
+            .owners
+            .iter()
+            .map(|owner| format!("{} ({}%)", owner.name, owner.share))
+            .collect();
+        result.push_str(&format!(
+            "\n{:<width$}  {:>7}  {:>10}  {}{}{}",
+            report.path,
+            report.lines,
+            report.bus_factor,
+            colors.commit,
+            owners.join(", "),
+            colors.reset,
+            width = width
+        ));
+
+        if !report.inactive_owners.is_empty() {
+            let inactive: Vec<String> = report
+                .owners
+                .iter()
+                .filter(|owner| report.inactive_owners.contains(&owner.name))
+                .map(|owner| format!("{} (last commit {})", owner.name, owner.last_commit))
+                .collect();
+            result.push_str(&format!(
+                "  {}inactive for {}+ months: {}{}",
+                colors.removed,
+                months,
+                inactive.join(", "),
+                colors.reset
+            ));
+        }
+    }
+
+    if reports.is_empty() {
+        result.push_str("\nNo text files found");
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bus_factor_per_subdirectory() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[
+                ("src/core/a.rs", "1\n2\n3\n4\n"),
+                ("src/ui/b.rs", "1\n2\n"),
+                ("src/main.rs", "main\n"),
+            ],
+            "Alice",
+            1749456964,
+            "init",
+        );
+        commit_files(
+            &repo,
+            &[("src/ui/c.rs", "1\n2\n")],
+            "Bob",
+            1749556964,
+            "add c",
+        );
+        commit_files(
+            &repo,
+            &[("src/ui/d.rs", "1\n2\n")],
+            "Carol",
+            1749656964,
+            "add d",
+        );
+
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "src".to_string(),
+        };
+        // Alice counts as inactive, everyone else committed after the cut-off
+        let reports = bus_factor_reports(&target, 1749500000).unwrap();
+        let summary: Vec<(&str, usize, usize)> = reports
+            .iter()
+            .map(|report| (report.path.as_str(), report.bus_factor, report.lines))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src/core/", 1, 4),
+                ("src/ (files)", 1, 1),
+                ("src/ui/", 2, 6)
+            ]
+        );
+
+        assert_eq!(reports[0].owners[0].name, "Alice");
+        assert_eq!(reports[0].owners[0].share, 100.0);
+        assert_eq!(reports[0].inactive_owners, vec!["Alice"]);
+        // Ties are broken by name: Alice and Bob own 2 of 6 lines each
+        let ui_owners: Vec<&str> = reports[2].owners.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(ui_owners, vec!["Alice", "Bob"]);
+
+        let json = serde_json::to_value(&reports).unwrap();
+        assert_eq!(json[0]["path"], "src/core/");
+        assert_eq!(json[0]["owners"][0]["last_commit"], "2025-06-09");
+    }
+}
@@ -1,4 +1,9 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use crate::copies::CopyDetection;
+use crate::git::OutputOptions;
 
 #[derive(Parser)]
 #[command(name = "wer")]
@@ -15,7 +20,9 @@ SMART PATH RESOLUTION:
   • Relative paths: "wer ../other-project/file.rs" finds git repo in ../other-project/
   • Current directory paths: "wer ./subdir/file.py" stays within current repo
   • Absolute paths: "wer ~/file.txt" or "wer /full/path" used directly
+  • "~", "~user" and "$VAR" / "${VAR}" are expanded, even inside quotes
   • Search ignores common directories (.git, node_modules, target, etc.)
+  • Deleted paths are looked up in history: "wer old.rs" shows who deleted it
 
 CROSS-REPOSITORY SUPPORT:
   wer can work with files in different git repositories by resolving relative paths:
@@ -23,19 +30,53 @@ CROSS-REPOSITORY SUPPORT:
   • "../../parent-project/src/" → finds git repo at ../../parent-project/
   • Each file is processed within its own git repository context
 
+BARE REPOSITORIES AND REVISIONS:
+  wer can read paths straight from git objects, without a working copy:
+  • "wer --git-dir /srv/mirror.git src/main.rs" uses a bare mirror directly
+  • Inside a bare repository, paths are looked up in the tree at HEAD
+  • "wer v1.2.0:src/main.rs" reads the path as it was at a revision
+  • "wer --ref release/2.x main.rs" does the same for every path, in any mode
+  • "wer --all-branches main.rs" finds the newest change on any branch and names the branch
+  • "wer -C ../other-project main.rs" runs as if started in another directory
+
+SUBMODULES AND WORKTREES:
+  • Paths inside a submodule report the superproject commit that bumped the submodule
+  • --recurse-submodules additionally shows the last commit inside the submodule
+  • Linked worktrees ("git worktree add") resolve to their own checked-out branch
+
 MULTIPLE MATCHES BEHAVIOR:
   When multiple files/directories with the same name are found:
   • Regular mode: Shows results for all matches, each prefixed with its path
-  • Blame mode: Shows error listing all matches and asks for full path specification
+  • Blame mode: Asks which match to use when run interactively, otherwise lists all matches
+  • --pick N selects the Nth match directly (useful in scripts)
+  • --all blames every match in sequence
+
+LINE HISTORY:
+  Blame shows the last commit per line; line history shows the whole story:
+  • "wer --line-history main.rs:42" lists every commit that changed line 42
+  • "wer -L 40,60 --history main.rs" does the same for a range of lines
+  • Ranges are tracked through edits and renames, each commit shown with its diff hunk
+
+MULTIPLE PATHS:
+  Several paths can be given at once, or piped in with --stdin:
+  • "wer a.rs b.rs src/" shows results for each path, prefixed with its path
+  • "git diff --name-only | wer --stdin" checks every file in a changeset
+  • Paths that fail are reported on stderr without stopping the others
+  • A path named like a subcommand (e.g. "reviewers") needs "wer -- reviewers" or "./reviewers"
 
 MODES:
   Regular mode (default): Shows the last commit that touched a file or directory
     Format: "61fcdda Author Name - 07 Jun 2025: commit message"
     Works with both files and directories
+    --created shows the commit that introduced the path, --both shows both
+    Uncommitted changes (modified, staged, untracked, ignored) are reported first
+    --timeline charts commits per month or week instead
     
   Blame mode (-b): Shows line-by-line git blame with syntax highlighting  
     Format: "61fcdda (Author Name - 07 Jun) | 1 | code content"
     Only works with files, not directories
+    --porcelain and --line-porcelain print "git blame" porcelain output for tools
+    --detect-moves and --detect-copies[=1-3] follow moved and copied lines like git's -M/-C
 
 EXAMPLES:
   wer Cargo.toml                      Find and show who last edited Cargo.toml
@@ -43,9 +84,35 @@ EXAMPLES:
   wer src/                            Show who last touched the src/ directory
   wer ../other-project/README.md      Show git info from different repository
   wer -b git.rs                       Find and show blame for src/git.rs
+  wer -b --pick 2 mod.rs              Blame the second of several files named mod.rs
   wer -d .                            Show only the date of last change
+  wer Cargo.toml src/                 Show who last edited each of several paths
+  git diff --name-only | wer --stdin  Show who last edited every changed file
   wer -l 3 src/                       Show last 3 contributors to src/ directory
-  wer -b -m ../docs/file.py           Show blame with commit messages from ../docs/ repo"#
+  wer --timeline --by-author src/     Chart monthly activity in src/ per top author
+  wer --created git.rs                Show who created src/git.rs, following renames
+  wer -b -m ../docs/file.py           Show blame with commit messages from ../docs/ repo
+  wer --git-dir mirror.git -b main.rs Blame a file in a bare repository
+  wer -b HEAD~5:src/main.rs           Blame a file as it was five commits ago
+  wer --ref origin/main src/          Show who last touched src/ on origin/main
+  wer --all-branches Cargo.toml       Show the newest change to Cargo.toml on any branch
+  wer -b -L 40,60 main.rs             Show blame for lines 40 to 60 only
+  wer -b --format html main.rs > b.html  Export blame as a standalone HTML page
+  wer -l 5 --format csv src/          Export the last 5 contributors as CSV
+  wer -b --format markdown main.rs    Show blame as a Markdown table
+  wer -l 3 --format json src/         Show the last 3 contributors, PRs and issues as JSON
+  wer -b --porcelain main.rs          Blame in "git blame --porcelain" format
+  wer -b --links main.rs              Show blame with a GitHub/GitLab/... link per line
+  wer -b --watch main.rs              Re-render blame as the file changes or commits land
+  wer -b --detect-copies=2 main.rs    Show where moved and copied lines originally came from
+  wer --line-history main.rs:42       Show every commit that changed line 42
+  wer reviewers main..feature         Suggest reviewers for the feature branch
+  wer codeowners check                Flag stale or missing CODEOWNERS entries
+  wer bus-factor src/                 Show which parts of src/ depend on one person
+  wer hotspots --since 6m src/        Show the most frequently changed files in src/
+  wer serve --port 8080               Browse the repository at http://127.0.0.1:8080/
+  wer lsp                             Serve blame annotations to an editor over stdio
+  wer completions zsh > _wer          Generate zsh completions (bash, fish, ... too)"#
 )]
 #[command(arg(clap::Arg::new("version")
     .short('v')
@@ -53,19 +120,85 @@ EXAMPLES:
     .action(clap::ArgAction::Version)
     .help("Print version")))]
 pub struct Cli {
-    /// File or directory path (searches automatically and works across repositories)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// File or directory paths (searches automatically and works across repositories)
     ///
     /// Supports multiple path types:
     /// • Filename only: "main.rs" (searches recursively in current repo)
     /// • Relative paths: "../other-repo/file.rs" (discovers appropriate git repo)
     /// • Directory paths: "./src/" or "../project/docs/"
     /// • Absolute paths: "~/file.txt" or "/full/path" (used directly)
-    pub path: Option<String>,
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<String>,
+
+    /// Read additional paths from stdin, one per line
+    ///
+    /// Useful for asking about a whole changeset: "git diff --name-only | wer --stdin"
+    #[arg(long = "stdin")]
+    pub stdin: bool,
 
     /// Show git blame with syntax highlighting (files only)
     #[arg(short = 'b', long = "blame")]
     pub blame: bool,
 
+    /// Restrict to a line range: "40,60", "40,+5" or a single line "42"
+    ///
+    /// In blame mode only these lines are shown; with --history, the range is traced
+    /// back through every commit that modified it.
+    #[arg(short = 'L', long = "lines", value_name = "START,END")]
+    pub lines: Option<String>,
+
+    /// Print blame in git's machine-readable porcelain format
+    ///
+    /// Same output as "git blame --porcelain", so tools that parse git blame can use wer's
+    /// path resolution. Commit details are printed the first time each commit appears.
+    #[arg(long = "porcelain", requires = "blame", conflicts_with_all = ["date_only", "commit_message"])]
+    pub porcelain: bool,
+
+    /// Like --porcelain, but repeat the commit details for every line
+    #[arg(long = "line-porcelain", requires = "blame", conflicts_with_all = ["porcelain", "date_only", "commit_message"])]
+    pub line_porcelain: bool,
+
+    /// Attribute lines moved within the file to the commit that wrote them, like "git blame -M"
+    ///
+    /// A moved block must hold at least 20 letters and digits, so blank lines and lone braces
+    /// stay with the commit that moved them. Blame shows where each moved line came from.
+    #[arg(long = "detect-moves", requires = "blame", conflicts_with_all = ["history", "line_history"])]
+    pub detect_moves: bool,
+
+    /// Also follow lines copied from other files, like "git blame -C" (implies --detect-moves)
+    ///
+    /// Level 1 looks in files changed by the same commit, 2 also in every file when the commit
+    /// created the file, 3 in every file for every commit (slow on large repositories).
+    #[arg(
+        long = "detect-copies",
+        value_name = "LEVEL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = clap::value_parser!(u8).range(1..=3),
+        requires = "blame",
+        conflicts_with_all = ["history", "line_history"]
+    )]
+    pub detect_copies: Option<u8>,
+
+    /// Show every commit that modified the lines given with -L, with the diff hunks
+    #[arg(long = "history", requires = "lines")]
+    pub history: bool,
+
+    /// Show the full history of lines in a file, e.g. "main.rs:42" or "main.rs:40,60"
+    ///
+    /// Shorthand for "-L 40,60 --history main.rs". Like "git log -L", the range is
+    /// tracked through edits and renames back to where the lines were introduced.
+    #[arg(
+        long = "line-history",
+        value_name = "FILE:LINES",
+        conflicts_with_all = ["lines", "paths", "stdin", "blame"]
+    )]
+    pub line_history: Option<String>,
+
     /// Show dates only
     /// Regular mode: "07 Jun 2025"
     /// Blame mode: "07 Jun | 1 | code content"
@@ -80,10 +213,328 @@ pub struct Cli {
     ///
     /// Lists the most recent N unique people who modified the path, with an
     /// indication if fewer contributors exist than requested.
-    #[arg(short = 'l', long = "last", value_name = "N")]
+    #[arg(
+        short = 'l',
+        long = "last",
+        value_name = "N",
+        conflicts_with_all = ["blame", "history", "line_history"]
+    )]
     pub last: Option<usize>,
 
+    /// Show the commit that created the path instead of the last one (regular mode only)
+    ///
+    /// Follows renames back to the file's original name, which is shown when it differs.
+    #[arg(
+        long = "created",
+        visible_alias = "first",
+        conflicts_with_all = ["last", "blame", "history", "line_history"]
+    )]
+    pub created: bool,
+
+    /// Show both the commit that created the path and the one that last edited it
+    #[arg(
+        long = "both",
+        conflicts_with_all = ["created", "blame", "history", "line_history"]
+    )]
+    pub both: bool,
+
+    /// Chart commits touching the path per month or week (regular mode only)
+    ///
+    /// Shows the last 24 months or 52 weeks as a sparkline, so inactive paths stand out.
+    #[arg(
+        long = "timeline",
+        conflicts_with_all = ["last", "created", "both", "all_branches", "blame", "history", "line_history"]
+    )]
+    pub timeline: bool,
+
+    /// Timeline bar period
+    #[arg(
+        long = "period",
+        value_enum,
+        default_value_t = TimelinePeriod::Month,
+        requires = "timeline"
+    )]
+    pub period: TimelinePeriod,
+
+    /// Add a timeline row for each of the top authors
+    #[arg(long = "by-author", requires = "timeline")]
+    pub by_author: bool,
+
+    /// Pick the Nth match when a name matches several files (as numbered in the match list)
+    #[arg(long = "pick", value_name = "N", conflicts_with = "all")]
+    pub pick: Option<usize>,
+
+    /// Blame every matching file in sequence instead of asking which one to use
+    #[arg(long = "all", requires = "blame")]
+    pub all: bool,
+
+    /// Inside a submodule, also show the last commit within the submodule itself
+    ///
+    /// By default, paths inside a submodule report the superproject commit that last
+    /// bumped the submodule pointer.
+    #[arg(long = "recurse-submodules")]
+    pub recurse_submodules: bool,
+
+    /// Print the web URL of every commit shown, and of every line in blame mode
+    ///
+    /// URLs are built from the "origin" remote for GitHub, GitLab, Bitbucket and Gitea. For
+    /// self-hosted servers, set "wer.commitUrlTemplate" and "wer.fileUrlTemplate" in git config.
+    /// Independently, hashes become clickable in terminals that support OSC 8 hyperlinks.
+    #[arg(
+        long = "links",
+        conflicts_with_all = ["date_only", "timeline", "porcelain", "line_porcelain"]
+    )]
+    pub links: bool,
+
+    /// Keep running and re-render whenever the path, HEAD or a ref changes
+    ///
+    /// Works in regular and blame mode. Lines whose attribution changed since the previous
+    /// render are marked in the left margin.
+    #[arg(
+        long = "watch",
+        conflicts_with_all = ["history", "line_history", "timeline", "all_branches", "stdin", "porcelain", "line_porcelain"]
+    )]
+    pub watch: bool,
+
+    /// Run as if wer was started in <DIR> instead of the current directory
+    #[arg(short = 'C', value_name = "DIR", global = true)]
+    pub directory: Option<String>,
+
+    /// Use the repository at <PATH> (e.g. a bare mirror) and read paths from its tree
+    ///
+    /// Paths are taken relative to the repository root and content is read from git
+    /// objects, so no working copy is needed.
+    #[arg(long = "git-dir", value_name = "PATH", global = true)]
+    pub git_dir: Option<PathBuf>,
+
+    /// Read history and content at <REV> instead of HEAD (branch, tag, remote ref or commit)
+    ///
+    /// Paths are looked up in the tree at that revision, so they don't need to exist in the
+    /// working copy. Works in all modes.
+    #[arg(long = "ref", value_name = "REV")]
+    pub rev: Option<String>,
+
+    /// Find the most recent change on any branch, and show which branches contain it
+    #[arg(
+        long = "all-branches",
+        conflicts_with_all = ["rev", "last", "created", "both", "blame", "history", "line_history"]
+    )]
+    pub all_branches: bool,
+
     /// Disable colored output and syntax highlighting
-    #[arg(long = "no-color")]
+    #[arg(long = "no-color", global = true)]
     pub no_color: bool,
+
+    /// Output format (json for normal mode, blame, bus-factor and hotspots, html for blame,
+    /// csv/tsv/markdown tables for normal and blame mode)
+    #[arg(long = "format", value_enum, default_value_t = Format::Text, global = true)]
+    pub format: Format,
+}
+
+/// How results are printed
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    /// Human-readable text with colors
+    Text,
+    /// Machine-readable JSON
+    Json,
+    /// Standalone HTML page (blame mode)
+    Html,
+    /// Comma-separated values, quoted where needed
+    Csv,
+    /// Tab-separated values, with tabs and newlines escaped
+    Tsv,
+    /// Markdown table
+    Markdown,
+}
+
+impl Cli {
+    /// Which moved and copied lines blame looks through
+    pub fn copy_detection(&self) -> CopyDetection {
+        CopyDetection {
+            moves: self.detect_moves,
+            copies: self.detect_copies.unwrap_or(0),
+        }
+    }
+
+    /// How commits are shown in blame and normal mode
+    pub fn output_options(&self) -> OutputOptions {
+        OutputOptions {
+            no_color: self.no_color,
+            date_only: self.date_only,
+            commit_message: self.commit_message,
+            links: self.links,
+            copies: self.copy_detection(),
+        }
+    }
+
+    /// Parse the command line, rejecting path-mode flags given alongside a subcommand
+    /// (clap's own check would also stop subcommands after global flags like --no-color)
+    pub fn parse_checked() -> Self {
+        let mut command = Self::command();
+        let matches = command.get_matches_mut();
+
+        if let Some((name, _)) = matches.subcommand() {
+            let conflicting = command
+                .get_arguments()
+                .filter(|arg| !arg.is_global_set())
+                .map(|arg| arg.get_id().as_str())
+                .find(|id| matches.value_source(id) == Some(ValueSource::CommandLine));
+            if let Some(id) = conflicting {
+                command
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        format!("'{}' cannot be used with the '{}' subcommand", id, name),
+                    )
+                    .exit();
+            }
+        }
+
+        Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Suggest reviewers for a change, ranked by ownership of the lines it touches
+    ///
+    /// The lines the change modifies or deletes are blamed as they were before it, and
+    /// their authors are ranked by how many of those lines they wrote, favouring recent
+    /// work. Authors of the change itself are left out.
+    Reviewers {
+        /// Commits to review as <BASE>..<HEAD> (default: HEAD against the default branch)
+        #[arg(value_name = "RANGE")]
+        range: Option<String>,
+
+        /// Maximum number of reviewers to suggest
+        #[arg(short = 'n', long = "max", value_name = "N", default_value_t = 5)]
+        max: usize,
+    },
+
+    /// Show how many people own the majority of each subdirectory's lines
+    ///
+    /// Lines are attributed with blame. A bus factor of 1 means one person wrote more than
+    /// half of the code; main owners who stopped committing are flagged.
+    BusFactor {
+        /// Directory whose subdirectories are reported (default: the current directory)
+        #[arg(value_name = "DIR")]
+        dir: Option<String>,
+
+        /// Flag main owners without commits in this many months
+        #[arg(long = "months", value_name = "N", default_value_t = 6)]
+        months: u32,
+    },
+
+    /// Rank files by how often and how much they changed
+    ///
+    /// Walks history (non-merge commits) and counts, per file that still exists, the commits,
+    /// distinct authors and lines added and removed.
+    Hotspots {
+        /// Directory to analyse (default: the current directory)
+        #[arg(value_name = "DIR")]
+        dir: Option<String>,
+
+        /// Only count commits since a date (2025-01-31) or period (90d, 6 weeks, 1 year)
+        #[arg(long = "since", value_name = "WHEN")]
+        since: Option<String>,
+
+        /// What to rank files by
+        #[arg(long = "sort", value_enum, default_value_t = HotspotSort::Commits)]
+        sort: HotspotSort,
+
+        /// Multiply the ranking by each file's current size in lines
+        #[arg(long = "by-size")]
+        by_size: bool,
+
+        /// Number of files to show
+        #[arg(short = 'n', long = "max", value_name = "N", default_value_t = 20)]
+        max: usize,
+    },
+
+    /// Check the CODEOWNERS file against history, or suggest one
+    Codeowners {
+        #[command(subcommand)]
+        action: CodeownersAction,
+    },
+
+    /// Run a language server on stdin/stdout that annotates open files with blame
+    ///
+    /// Editors get a code lens above every block of lines from the same commit, an inlay hint
+    /// with the last author of every line and the full commit on hover. Blame is recomputed
+    /// when a file is saved.
+    Lsp,
+
+    /// Browse the repository in a web browser
+    ///
+    /// Serves the tree at HEAD with the last commit of every entry, blame pages with syntax
+    /// highlighting, and the same data as JSON under /api/tree/PATH and /api/blame/PATH.
+    Serve {
+        /// Port to listen on (0 picks a free one)
+        #[arg(long = "port", value_name = "PORT", default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on; the default only accepts connections from this machine
+        #[arg(long = "host", value_name = "ADDRESS", default_value = "127.0.0.1")]
+        host: String,
+    },
+
+    /// Print a shell completion script
+    ///
+    /// In bash, zsh and fish, paths complete to files tracked by git, including bare file
+    /// names that wer finds anywhere in the repository. Install it with e.g.
+    /// "wer completions bash > ~/.local/share/bash-completion/completions/wer".
+    Completions {
+        /// Shell to generate the script for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the man page in roff format, e.g. "wer man > /usr/local/share/man/man1/wer.1"
+    Man,
+}
+
+/// Period of a --timeline bar
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum TimelinePeriod {
+    Week,
+    Month,
+}
+
+/// What hotspots are ranked by
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum HotspotSort {
+    /// Number of commits that changed the file
+    Commits,
+    /// Number of distinct authors
+    Authors,
+    /// Lines added plus lines removed
+    Churn,
+}
+
+#[derive(Subcommand)]
+pub enum CodeownersAction {
+    /// Flag rules whose owners no longer work on their files, and files without owners
+    ///
+    /// Reads CODEOWNERS from .github/, .gitlab/, the root or docs/ at HEAD. Owners given as
+    /// @handles are matched against commit emails and names; teams are not checked.
+    Check {
+        /// How many months of history count as recent
+        #[arg(long = "months", value_name = "N", default_value_t = 12)]
+        months: u32,
+    },
+
+    /// Print a CODEOWNERS file based on who committed to each directory recently
+    Suggest {
+        /// How many months of history to base ownership on
+        #[arg(long = "months", value_name = "N", default_value_t = 12)]
+        months: u32,
+
+        /// How many directory levels get their own rule
+        #[arg(long = "depth", value_name = "N", default_value_t = 2)]
+        depth: usize,
+
+        /// Owners listed per rule
+        #[arg(long = "owners", value_name = "N", default_value_t = 2)]
+        owners: usize,
+    },
 }
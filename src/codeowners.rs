@@ -0,0 +1,593 @@
+use anyhow::{Result, anyhow};
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::git::{ColorScheme, first_parent_diff, open_repo};
+use crate::utils::format_timestamp_day_month_year;
+
+/// Where GitHub, GitLab and Gitea look for the CODEOWNERS file, in order
+const CODEOWNERS_LOCATIONS: [&str; 4] = [
+    ".github/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+];
+
+const SECONDS_PER_MONTH: i64 = 30 * 24 * 60 * 60;
+
+/// A "pattern owner..." line of a CODEOWNERS file
+struct Rule {
+    line: usize,
+    pattern: String,
+    owners: Vec<String>,
+}
+
+impl Rule {
+    /// Whether the rule applies to a file, using CODEOWNERS (gitignore-style) pattern rules
+    fn matches(&self, path: &str) -> bool {
+        let mut pattern = self.pattern.as_str();
+        let dir_only = pattern.ends_with('/');
+        pattern = pattern.trim_end_matches('/');
+
+        // Patterns without an inner slash match at any depth
+        let anchored = pattern.starts_with('/') || pattern.contains('/');
+        pattern = pattern.trim_start_matches('/');
+
+        let mut pattern_segments: Vec<&str> = pattern.split('/').collect();
+        if !anchored {
+            pattern_segments.insert(0, "**");
+        }
+        let path_segments: Vec<&str> = path.split('/').collect();
+
+        // Only a pattern naming a directory (a trailing "/" or a last segment without
+        // wildcards) owns everything below it; "docs/*" is the files directly in docs
+        let last = pattern_segments.last().copied().unwrap_or("");
+        let names_directory = dir_only || !last.contains(['*', '?']);
+        (1..=path_segments.len()).any(|len| {
+            let is_file = len == path_segments.len();
+            let applies = if is_file { !dir_only } else { names_directory };
+            applies && match_segments(&pattern_segments, &path_segments[..len])
+        })
+    }
+}
+
+/// Someone who committed to the repository
+#[derive(Clone)]
+struct Author {
+    name: String,
+    email: String,
+}
+
+impl Author {
+    /// People are matched by email, case-insensitively
+    fn key(&self) -> String {
+        self.email.to_lowercase()
+    }
+
+    /// Whether a CODEOWNERS owner ("@handle" or an email) refers to this author
+    /// Handles are matched against the email's user part (including GitHub noreply
+    /// addresses) and the name without spaces, as git history doesn't know usernames
+    fn is_owner(&self, owner: &str) -> bool {
+        let owner = owner.to_lowercase();
+        let email = self.email.to_lowercase();
+        if !owner.starts_with('@') {
+            return owner == email;
+        }
+
+        let handle = &owner[1..];
+        let user = email.split('@').next().unwrap_or("");
+        let noreply_user = user.split_once('+').map_or(user, |(_, user)| user);
+        let name = self.name.to_lowercase().replace(' ', "");
+
+        handle == user || handle == noreply_user || handle == name
+    }
+}
+
+/// One commit in the activity window: its author and the paths it changed
+struct Activity {
+    author: Author,
+    paths: Vec<String>,
+}
+
+/// Check the repository's CODEOWNERS file against recent history
+/// Flags rules matching no files, rules without owners, rules whose owners haven't touched
+/// their files in the last `months` months while others have, and files no rule covers
+pub fn check_codeowners(git_dir: Option<&Path>, months: u32, no_color: bool) -> Result<String> {
+    let repo = open_repo(git_dir)?;
+    let since = chrono::Utc::now().timestamp() - i64::from(months) * SECONDS_PER_MONTH;
+    check_codeowners_since(&repo, since, no_color)
+}
+
+fn check_codeowners_since(repo: &Repository, since: i64, no_color: bool) -> Result<String> {
+    let colors = ColorScheme::new(no_color);
+    let (location, content) = read_codeowners(repo)?;
+    let rules = parse_codeowners(&content);
+    let files = tracked_files(repo)?;
+
+    let mut matched_files = vec![0; rules.len()];
+    let mut uncovered: BTreeMap<String, usize> = BTreeMap::new();
+    for file in &files {
+        match owning_rule(&rules, file) {
+            Some(index) if !rules[index].owners.is_empty() => matched_files[index] += 1,
+            Some(index) => {
+                matched_files[index] += 1;
+                *uncovered.entry(top_directory(file)).or_default() += 1;
+            }
+            None => *uncovered.entry(top_directory(file)).or_default() += 1,
+        }
+    }
+
+    // Recent commits per rule, by author
+    let mut contributors: Vec<HashMap<String, (Author, usize)>> = vec![HashMap::new(); rules.len()];
+    for activity in recent_activity(repo, since)? {
+        let mut seen_rules: Vec<usize> = activity
+            .paths
+            .iter()
+            .filter_map(|path| owning_rule(&rules, path))
+            .collect();
+        seen_rules.sort_unstable();
+        seen_rules.dedup();
+
+        for index in seen_rules {
+            contributors[index]
+                .entry(activity.author.key())
+                .or_insert_with(|| (activity.author.clone(), 0))
+                .1 += 1;
+        }
+    }
+
+    let mut problems = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        let header = format!(
+            "{}line {}{}: {} {}",
+            colors.commit,
+            rule.line,
+            colors.reset,
+            rule.pattern,
+            rule.owners.join(" ")
+        );
+
+        if matched_files[index] == 0 {
+            problems.push(format!("{}\n  matches no tracked files", header));
+            continue;
+        }
+        if rule.owners.is_empty() {
+            problems.push(format!(
+                "{}\n  has no owners ({} files)",
+                header, matched_files[index]
+            ));
+            continue;
+        }
+
+        // Teams can't be resolved from history, so only individual owners are checked
+        let people: Vec<&String> = rule
+            .owners
+            .iter()
+            .filter(|owner| !owner.contains('/'))
+            .collect();
+        let recent = &contributors[index];
+        if people.is_empty() || recent.is_empty() {
+            continue;
+        }
+
+        let inactive: Vec<&str> = people
+            .iter()
+            .filter(|owner| !recent.values().any(|(author, _)| author.is_owner(owner)))
+            .map(|owner| owner.as_str())
+            .collect();
+        if inactive.is_empty() {
+            continue;
+        }
+
+        let mut top: Vec<&(Author, usize)> = recent.values().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.name.cmp(&b.0.name)));
+        let top: Vec<String> = top
+            .iter()
+            .take(3)
+            .map(|(author, commits)| format!("{} <{}> ({})", author.name, author.email, commits))
+            .collect();
+        let label = if inactive.len() == people.len() {
+            "stale"
+        } else {
+            "partly stale"
+        };
+        problems.push(format!(
+            "{}\n  {}{}{}: no recent commits by {}; most active: {}",
+            header,
+            colors.removed,
+            label,
+            colors.reset,
+            inactive.join(", "),
+            top.join(", ")
+        ));
+    }
+
+    let mut result = format!(
+        "Checked {} ({} rules) against commits since {}{}{}",
+        location,
+        rules.len(),
+        colors.date,
+        format_timestamp_day_month_year(since),
+        colors.reset
+    );
+    for problem in &problems {
+        result.push_str(&format!("\n\n{}", problem));
+    }
+
+    let uncovered_total: usize = uncovered.values().sum();
+    if uncovered_total > 0 {
+        let directories: Vec<String> = uncovered
+            .iter()
+            .map(|(directory, count)| format!("{} ({})", directory, count))
+            .collect();
+        result.push_str(&format!(
+            "\n\n{}ownerless{}: {} of {} files have no owner: {}",
+            colors.removed,
+            colors.reset,
+            uncovered_total,
+            files.len(),
+            directories.join(", ")
+        ));
+    }
+
+    if problems.is_empty() && uncovered_total == 0 {
+        result.push_str("\nNo problems found");
+    }
+
+    Ok(result)
+}
+
+/// Propose a CODEOWNERS file from who committed where in the last `months` months
+/// Each directory up to `depth` levels gets its `owners` most frequent committers, and
+/// directories whose owners match their parent's are left to the parent's rule
+pub fn suggest_codeowners(
+    git_dir: Option<&Path>,
+    months: u32,
+    depth: usize,
+    owners: usize,
+) -> Result<String> {
+    let repo = open_repo(git_dir)?;
+    let since = chrono::Utc::now().timestamp() - i64::from(months) * SECONDS_PER_MONTH;
+    suggest_codeowners_since(&repo, since, depth, owners)
+}
+
+fn suggest_codeowners_since(
+    repo: &Repository,
+    since: i64,
+    depth: usize,
+    owners: usize,
+) -> Result<String> {
+    let files = tracked_files(repo)?;
+    let activity = recent_activity(repo, since)?;
+    if activity.is_empty() {
+        return Err(anyhow!(
+            "No commits since {}, try a longer --months window",
+            format_timestamp_day_month_year(since)
+        ));
+    }
+
+    // Commits per directory (the root is "") and author, for paths that still exist
+    let mut directories: BTreeMap<String, HashMap<String, (Author, usize)>> = BTreeMap::new();
+    for activity in &activity {
+        let mut touched: Vec<String> = Vec::new();
+        for path in activity
+            .paths
+            .iter()
+            .filter(|path| files.binary_search(path).is_ok())
+        {
+            let segments: Vec<&str> = path.split('/').collect();
+            for level in 0..segments.len().min(depth + 1) {
+                touched.push(segments[..level].join("/"));
+            }
+        }
+        touched.sort_unstable();
+        touched.dedup();
+
+        for directory in touched {
+            directories
+                .entry(directory)
+                .or_default()
+                .entry(activity.author.key())
+                .or_insert_with(|| (activity.author.clone(), 0))
+                .1 += 1;
+        }
+    }
+
+    let mut result = format!(
+        "# Suggested by wer from commits since {}\n# Review before use: owners are emails as recorded in git history",
+        format_timestamp_day_month_year(since)
+    );
+    let mut emitted: Vec<(String, Vec<String>)> = Vec::new();
+    for (directory, authors) in &directories {
+        let mut ranked: Vec<&(Author, usize)> = authors.values().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.email.cmp(&b.0.email)));
+        let top: Vec<String> = ranked
+            .iter()
+            .take(owners)
+            .map(|(author, _)| author.email.clone())
+            .collect();
+
+        // Skip directories that would repeat the owners of the rule covering them
+        let inherited = emitted
+            .iter()
+            .rev()
+            .find(|(parent, _)| parent.is_empty() || directory.starts_with(&format!("{}/", parent)))
+            .map(|(_, owners)| owners);
+        if inherited == Some(&top) {
+            continue;
+        }
+
+        let pattern = if directory.is_empty() {
+            "*".to_string()
+        } else {
+            format!("/{}/", directory)
+        };
+        result.push_str(&format!("\n{} {}", pattern, top.join(" ")));
+        emitted.push((directory.clone(), top));
+    }
+
+    Ok(result)
+}
+
+/// Find and read the CODEOWNERS file from the tree at HEAD
+fn read_codeowners(repo: &Repository) -> Result<(&'static str, String)> {
+    let tree = repo.head()?.peel_to_tree()?;
+    for location in CODEOWNERS_LOCATIONS {
+        if let Ok(entry) = tree.get_path(Path::new(location)) {
+            let blob = entry.to_object(repo)?.peel_to_blob()?;
+            return Ok((
+                location,
+                String::from_utf8_lossy(blob.content()).to_string(),
+            ));
+        }
+    }
+
+    Err(anyhow!(
+        "No CODEOWNERS file found (looked in {}). Try 'wer codeowners suggest'",
+        CODEOWNERS_LOCATIONS.join(", ")
+    ))
+}
+
+/// Parse CODEOWNERS content into rules, skipping comments, blank lines and section headers
+fn parse_codeowners(content: &str) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split_once(" #").map_or(line, |(rule, _)| rule).trim();
+        // GitLab sections look like "[Section name] @owner"
+        if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let Some(pattern) = parts.next() else {
+            continue;
+        };
+        rules.push(Rule {
+            line: index + 1,
+            pattern: pattern.to_string(),
+            owners: parts.map(str::to_string).collect(),
+        });
+    }
+    rules
+}
+
+/// The rule that owns a path: the last one that matches, as in GitHub and GitLab
+fn owning_rule(rules: &[Rule], path: &str) -> Option<usize> {
+    rules.iter().rposition(|rule| rule.matches(path))
+}
+
+/// Match path segments against pattern segments, where "**" spans any number of segments
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(&"**") => (0..=path.len()).any(|skip| match_segments(&pattern[1..], &path[skip..])),
+        Some(segment) => {
+            !path.is_empty()
+                && match_wildcard(segment.as_bytes(), path[0].as_bytes())
+                && match_segments(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+/// Match a single path segment against a pattern with "*" and "?" wildcards
+fn match_wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') => (0..=text.len()).any(|skip| match_wildcard(&pattern[1..], &text[skip..])),
+        Some(b'?') => !text.is_empty() && match_wildcard(&pattern[1..], &text[1..]),
+        Some(byte) => text.first() == Some(byte) && match_wildcard(&pattern[1..], &text[1..]),
+    }
+}
+
+/// The first directory of a path, or the path itself for files at the root
+fn top_directory(path: &str) -> String {
+    match path.split_once('/') {
+        Some((directory, _)) => format!("{}/", directory),
+        None => path.to_string(),
+    }
+}
+
+/// All files in the tree at HEAD, sorted
+fn tracked_files(repo: &Repository) -> Result<Vec<String>> {
+    let tree = repo.head()?.peel_to_tree()?;
+    let mut files = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |parent, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            files.push(format!("{}{}", parent, entry.name().unwrap_or("")));
+        }
+        TreeWalkResult::Ok
+    })?;
+    files.sort();
+    Ok(files)
+}
+
+/// Non-merge commits reachable from HEAD made at or after `since`, with the paths they changed
+fn recent_activity(repo: &Repository, since: i64) -> Result<Vec<Activity>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+    revwalk.push_head()?;
+
+    let mut activity = Vec::new();
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        if commit.time().seconds() < since {
+            break;
+        }
+        if commit.parent_count() > 1 {
+            continue;
+        }
+
+        let diff = first_parent_diff(repo, &commit, None)?;
+        let paths = diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+
+        let author = commit.author();
+        activity.push(Activity {
+            author: Author {
+                name: author.name().unwrap_or("Unknown").to_string(),
+                email: author.email().unwrap_or("").to_string(),
+            },
+            paths,
+        });
+    }
+
+    Ok(activity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use tempfile::tempdir;
+
+    fn rule(pattern: &str) -> Rule {
+        Rule {
+            line: 1,
+            pattern: pattern.to_string(),
+            owners: vec!["@owner".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        assert!(rule("*").matches("src/main.rs"));
+        assert!(rule("*.rs").matches("src/main.rs"));
+        assert!(!rule("*.rs").matches("README.md"));
+        assert!(rule("/src/").matches("src/git/mod.rs"));
+        assert!(!rule("/src/").matches("lib/src/mod.rs"));
+        assert!(rule("docs/").matches("lib/docs/guide.md"));
+        assert!(!rule("docs/").matches("docs"));
+        assert!(rule("src/*.rs").matches("src/main.rs"));
+        assert!(!rule("src/*.rs").matches("src/git/mod.rs"));
+        assert!(rule("**/tests/*.rs").matches("a/b/tests/it.rs"));
+        assert!(rule("/build/logs").matches("build/logs/today.log"));
+        assert!(rule("Cargo.toml").matches("crates/core/Cargo.toml"));
+
+        // A wildcard segment matches one level, a directory everything below it
+        assert!(rule("docs/*").matches("docs/getting-started.md"));
+        assert!(!rule("docs/*").matches("docs/build-app/troubleshooting.md"));
+        assert!(rule("docs/").matches("docs/build-app/troubleshooting.md"));
+        assert!(rule("docs/*/").matches("docs/build-app/troubleshooting.md"));
+        assert!(rule("docs/**").matches("docs/build-app/troubleshooting.md"));
+    }
+
+    #[test]
+    fn test_parse_codeowners_and_last_match_wins() {
+        let rules = parse_codeowners(
+            "# Owners\n\n* @everyone\n/src/ @alice @org/core # core code\n[Docs]\n/docs/\n",
+        );
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[1].line, 4);
+        assert_eq!(rules[1].owners, vec!["@alice", "@org/core"]);
+        assert!(rules[2].owners.is_empty());
+
+        assert_eq!(owning_rule(&rules, "src/main.rs"), Some(1));
+        assert_eq!(owning_rule(&rules, "README.md"), Some(0));
+    }
+
+    #[test]
+    fn test_owner_matching() {
+        let author = Author {
+            name: "Jane Doe".to_string(),
+            email: "12345+jdoe@users.noreply.github.com".to_string(),
+        };
+        assert!(author.is_owner("@jdoe"));
+        assert!(author.is_owner("@JaneDoe"));
+        assert!(author.is_owner("12345+jdoe@users.noreply.github.com"));
+        assert!(!author.is_owner("@alice"));
+    }
+
+    #[test]
+    fn test_check_and_suggest() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[
+                (
+                    ".github/CODEOWNERS",
+                    "/src/ @alice\n/docs/ @bob\n/old/ @carol\n",
+                ),
+                ("src/main.rs", "main\n"),
+                ("docs/guide.md", "guide\n"),
+                ("scripts/build.sh", "build\n"),
+            ],
+            "Alice",
+            1749456964,
+            "init",
+        );
+        commit_files(
+            &repo,
+            &[("docs/guide.md", "guide v2\n")],
+            "Dave",
+            1749556964,
+            "rewrite guide",
+        );
+        commit_files(
+            &repo,
+            &[("docs/guide.md", "guide v3\n")],
+            "Dave",
+            1749656964,
+            "fix typo",
+        );
+
+        let output = check_codeowners_since(&repo, 1749400000, true).unwrap();
+        assert!(
+            output.contains("line 2: /docs/ @bob\n  stale: no recent commits by @bob; most active: Dave <dave@example.com> (2), Alice <alice@example.com> (1)"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("line 3: /old/ @carol\n  matches no tracked files"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("/src/ @alice\n"), "{}", output);
+        assert!(
+            output.contains("ownerless: 2 of 4 files have no owner: .github/ (1), scripts/ (1)"),
+            "{}",
+            output
+        );
+
+        let suggested = suggest_codeowners_since(&repo, 1749400000, 1, 1).unwrap();
+        let rules: Vec<&str> = suggested
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                "* dave@example.com",
+                "/.github/ alice@example.com",
+                "/scripts/ alice@example.com",
+                "/src/ alice@example.com"
+            ],
+            "{}",
+            suggested
+        );
+    }
+}
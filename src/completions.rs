@@ -0,0 +1,245 @@
+use anyhow::Result;
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+use git2::Repository;
+use std::collections::BTreeSet;
+
+use crate::cli::Cli;
+
+/// Completion script for `shell`, generated from the command line definition
+/// For bash, zsh and fish, path arguments are completed by `wer __complete` instead of a
+/// directory listing, so tracked files anywhere in the repository can be offered by name
+pub fn completion_script(shell: Shell) -> String {
+    let mut command = Cli::command();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, "wer", &mut script);
+    let script = String::from_utf8_lossy(&script).into_owned();
+
+    match shell {
+        Shell::Bash => format!("{}{}", script, bash_path_completion(&command)),
+        Shell::Zsh => zsh_path_completion(&script),
+        Shell::Fish => format!(
+            "{}complete -c wer -n \"__fish_wer_needs_command\" -f -a \"(wer __complete (commandline -ct) 2>/dev/null)\"\n",
+            script
+        ),
+        _ => script,
+    }
+}
+
+/// The man page, in roff format
+pub fn man_page() -> Result<String> {
+    let mut page = Vec::new();
+    clap_mangen::Man::new(Cli::command()).render(&mut page)?;
+    Ok(String::from_utf8_lossy(&page).into_owned())
+}
+
+/// Print the tracked paths completing `prefix`, for the completion scripts
+/// Outside a repository, or on any error, nothing is printed and the shell falls back to files
+pub fn print_path_completions(prefix: &str) {
+    let Ok(repo) = Repository::discover(".") else {
+        return;
+    };
+    let cwd = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| cwd.canonicalize().ok());
+    let relative_cwd = match (repo.workdir().and_then(|dir| dir.canonicalize().ok()), cwd) {
+        (Some(workdir), Some(cwd)) => match cwd.strip_prefix(&workdir) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => return,
+        },
+        _ => String::new(),
+    };
+
+    if let Ok(candidates) = complete_paths(&repo, &relative_cwd, prefix) {
+        for candidate in candidates {
+            println!("{}", candidate);
+        }
+    }
+}
+
+/// Tracked paths completing `prefix`: paths relative to `relative_cwd`, one directory level at a
+/// time, and for a bare name the files and directories of that name anywhere in the repository
+fn complete_paths(repo: &Repository, relative_cwd: &str, prefix: &str) -> Result<Vec<String>> {
+    // The index has staged files too; bare repositories only have the tree at HEAD
+    let mut tracked = Vec::new();
+    if repo.is_bare() {
+        let tree = repo.head()?.peel_to_tree()?;
+        tree.walk(git2::TreeWalkMode::PreOrder, |parent, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                tracked.push(format!("{}{}", parent, entry.name().unwrap_or("")));
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+    } else {
+        for entry in repo.index()?.iter() {
+            tracked.push(String::from_utf8_lossy(&entry.path).into_owned());
+        }
+    }
+
+    let base = if relative_cwd.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", relative_cwd.trim_end_matches('/'))
+    };
+
+    let mut candidates = BTreeSet::new();
+    for path in &tracked {
+        if let Some(relative) = path.strip_prefix(&base)
+            && let Some(rest) = relative.strip_prefix(prefix)
+        {
+            match rest.find('/') {
+                Some(slash) => candidates.insert(relative[..prefix.len() + slash + 1].to_string()),
+                None => candidates.insert(relative.to_string()),
+            };
+        }
+
+        if prefix.is_empty() || prefix.contains('/') {
+            continue;
+        }
+        let components: Vec<&str> = path.split('/').collect();
+        for (index, component) in components.iter().enumerate() {
+            if component.starts_with(prefix) {
+                if index + 1 < components.len() {
+                    candidates.insert(format!("{}/", component));
+                } else {
+                    candidates.insert(component.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(candidates.into_iter().collect())
+}
+
+/// Bash: after the generated completion, add tracked paths unless a subcommand was given or
+/// an option is waiting for its value
+fn bash_path_completion(command: &Command) -> String {
+    let mut subcommands: Vec<&str> = command.get_subcommands().map(Command::get_name).collect();
+    subcommands.push("help");
+
+    let mut value_options = Vec::new();
+    for arg in command.get_arguments() {
+        if arg.is_positional() || !arg.get_action().takes_values() {
+            continue;
+        }
+        if let Some(long) = arg.get_long() {
+            value_options.push(format!("--{}", long));
+        }
+        if let Some(short) = arg.get_short() {
+            value_options.push(format!("-{}", short));
+        }
+    }
+
+    format!(
+        r#"
+_wer_with_paths() {{
+    _wer "$@"
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}" word
+    [[ "$cur" == -* ]] && return
+    case "$prev" in
+        {}) return ;;
+    esac
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        case "$word" in
+            {}) return ;;
+        esac
+    done
+    local IFS=$'\n'
+    COMPREPLY+=( $(wer __complete "$cur" 2>/dev/null) )
+    [[ ${{#COMPREPLY[@]}} -eq 1 && "${{COMPREPLY[0]}}" == */ ]] && compopt -o nospace
+}}
+
+complete -F _wer_with_paths -o bashdefault -o default wer
+"#,
+        value_options.join("|"),
+        subcommands.join("|")
+    )
+}
+
+/// Zsh: complete the PATH arguments with tracked paths, falling back to files
+fn zsh_path_completion(script: &str) -> String {
+    let helper = r#"
+(( $+functions[_wer_tracked_paths] )) ||
+_wer_tracked_paths() {
+    local -a paths dirs files
+    paths=(${(f)"$(wer __complete "$PREFIX" 2>/dev/null)"})
+    dirs=(${(M)paths:#*/})
+    files=(${paths:#*/})
+    (( $#paths )) || { _files; return }
+    compadd -S '' -a dirs
+    compadd -a files
+}
+"#;
+
+    let mut result = String::new();
+    for line in script.lines() {
+        if line.contains("::paths -- ") {
+            result.push_str(&line.replace(":_default'", ":_wer_tracked_paths'"));
+        } else {
+            result.push_str(line);
+        }
+        result.push('\n');
+        if line == "autoload -U is-at-least" {
+            result.push_str(helper);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_complete_tracked_paths() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[
+                ("src/main.rs", "fn main() {}\n"),
+                ("src/git/mod.rs", "\n"),
+                ("docs/manual.md", "\n"),
+                ("Makefile", "\n"),
+            ],
+            "Alice",
+            1749456964,
+            "init",
+        );
+
+        // From the root, one level at a time, and bare names from anywhere
+        assert_eq!(
+            complete_paths(&repo, "", "").unwrap(),
+            vec!["Makefile", "docs/", "src/"]
+        );
+        assert_eq!(
+            complete_paths(&repo, "", "ma").unwrap(),
+            vec!["main.rs", "manual.md"]
+        );
+        assert_eq!(
+            complete_paths(&repo, "", "src/").unwrap(),
+            vec!["src/git/", "src/main.rs"]
+        );
+        assert_eq!(complete_paths(&repo, "", "gi").unwrap(), vec!["git/"]);
+
+        // Relative to a subdirectory
+        assert_eq!(
+            complete_paths(&repo, "src", "").unwrap(),
+            vec!["git/", "main.rs"]
+        );
+        assert_eq!(complete_paths(&repo, "src", "M").unwrap(), vec!["Makefile"]);
+    }
+
+    #[test]
+    fn test_scripts_call_path_endpoint() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = completion_script(shell);
+            assert!(script.contains("wer __complete"), "{}", script);
+            assert!(script.contains("bus-factor"));
+        }
+        assert!(completion_script(Shell::Zsh).contains(":_wer_tracked_paths'"));
+        assert!(man_page().unwrap().contains(".TH wer"));
+    }
+}
@@ -0,0 +1,366 @@
+use anyhow::Result;
+use git2::{BlameOptions, Delta, Oid, Repository};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::git::blame_at;
+
+/// Like git, a block of lines only counts as moved or copied when it holds at least this many
+/// alphanumeric characters, so blank lines and lone braces aren't traced anywhere
+const MIN_MATCH_CHARACTERS: usize = 20;
+
+/// Which moved and copied lines blame looks through, like `git blame -M` and `-C`
+#[derive(Clone, Copy, Default)]
+pub struct CopyDetection {
+    /// Lines moved within the file
+    pub moves: bool,
+    /// 0 for none, 1 for lines moved or copied from files changed in the same commit,
+    /// 2 also from any file when the commit created the file, 3 from any file in any commit
+    pub copies: u8,
+}
+
+/// Where a moved or copied line was last changed
+#[derive(Clone)]
+pub struct LineSource {
+    pub commit_id: Oid,
+    /// Path of the file the line came from, in that commit
+    pub path: PathBuf,
+    /// Line number in that commit's version of the file
+    pub line: usize,
+}
+
+impl CopyDetection {
+    pub fn is_enabled(&self) -> bool {
+        self.moves || self.copies > 0
+    }
+
+    /// Set the matching libgit2 blame flags
+    pub fn configure(&self, options: &mut BlameOptions) {
+        if !self.is_enabled() {
+            return;
+        }
+        options
+            .track_copies_same_file(true)
+            .track_copies_same_commit_moves(self.copies >= 1)
+            .track_copies_same_commit_copies(self.copies >= 2)
+            .track_copies_any_commit_copies(self.copies >= 3);
+    }
+
+    /// The origin of lines that `blame` attributes to the commit that moved or copied them
+    /// there, keyed by line number in the blamed file
+    ///
+    /// libgit2 accepts the copy tracking flags but doesn't act on them yet, so every line a
+    /// commit added is looked up in the files of its first parent chosen by the detection
+    /// level, and blocks found there are blamed in that file instead, again through moves and
+    /// copies.
+    pub fn line_sources(
+        &self,
+        repo: &Repository,
+        blame: &git2::Blame,
+        path: &Path,
+    ) -> Result<HashMap<usize, LineSource>> {
+        if !self.is_enabled() {
+            return Ok(HashMap::new());
+        }
+        self.trace(repo, blame, path, None)
+    }
+
+    /// `line_sources` for the `wanted` lines only, or all lines when None
+    fn trace(
+        &self,
+        repo: &Repository,
+        blame: &git2::Blame,
+        path: &Path,
+        wanted: Option<&HashSet<usize>>,
+    ) -> Result<HashMap<usize, LineSource>> {
+        let mut contents: HashMap<(Oid, PathBuf), Option<Vec<String>>> = HashMap::new();
+        // Per source file in a parent commit: (line in the blamed file, line in the source)
+        let mut copied: HashMap<(Oid, PathBuf), Vec<(usize, usize)>> = HashMap::new();
+        for hunk in blame.iter() {
+            let first = hunk.final_start_line();
+            let hunk_range = first..first + hunk.lines_in_hunk();
+            if wanted.is_some_and(|wanted| !hunk_range.clone().any(|line| wanted.contains(&line))) {
+                continue;
+            }
+            let commit = repo.find_commit(hunk.final_commit_id())?;
+            let Ok(parent) = commit.parent(0) else {
+                continue;
+            };
+            let hunk_path = hunk.path().unwrap_or(path).to_path_buf();
+            let Some(lines) = file_lines(repo, &mut contents, commit.id(), &hunk_path)? else {
+                continue;
+            };
+            let start = (hunk.orig_start_line() - 1).min(lines.len());
+            let end = (start + hunk.lines_in_hunk()).min(lines.len());
+            let hunk_lines = lines[start..end].to_vec();
+
+            let mut candidates = Vec::new();
+            for candidate in self.candidate_paths(repo, &commit, &parent, &hunk_path)? {
+                if let Some(lines) = file_lines(repo, &mut contents, parent.id(), &candidate)? {
+                    candidates.push((candidate, lines.clone()));
+                }
+            }
+
+            for (offset, source_path, source_line) in find_copies(&hunk_lines, &candidates) {
+                let line = first + offset;
+                if wanted.is_none_or(|wanted| wanted.contains(&line)) {
+                    copied
+                        .entry((parent.id(), source_path))
+                        .or_default()
+                        .push((line, source_line));
+                }
+            }
+        }
+
+        let mut sources = HashMap::new();
+        for ((parent_id, source_path), lines) in copied {
+            let source_blame = blame_at(repo, &source_path, Some(parent_id), *self)?;
+            // Lines can move several times; follow them to where they were last changed
+            let source_lines: HashSet<usize> = lines.iter().map(|&(_, source)| source).collect();
+            let earlier = self.trace(repo, &source_blame, &source_path, Some(&source_lines))?;
+            for (line, source_line) in lines {
+                if let Some(source) = earlier.get(&source_line) {
+                    sources.insert(line, source.clone());
+                } else if let Some(source_hunk) = source_blame.get_line(source_line) {
+                    let source = LineSource {
+                        commit_id: source_hunk.final_commit_id(),
+                        path: source_hunk
+                            .path()
+                            .map_or_else(|| source_path.clone(), Path::to_path_buf),
+                        line: source_hunk.orig_start_line() + source_line
+                            - source_hunk.final_start_line(),
+                    };
+                    sources.insert(line, source);
+                }
+            }
+        }
+
+        Ok(sources)
+    }
+
+    /// Files of `parent` that lines added by `commit` to `path` may have come from, the file
+    /// itself first
+    fn candidate_paths(
+        &self,
+        repo: &Repository,
+        commit: &git2::Commit,
+        parent: &git2::Commit,
+        path: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        let parent_tree = parent.tree()?;
+        let existed = parent_tree.get_path(path).is_ok();
+        let mut paths = Vec::new();
+        if existed {
+            paths.push(path.to_path_buf());
+        }
+
+        if self.copies >= 3 || (self.copies == 2 && !existed) {
+            parent_tree.walk(git2::TreeWalkMode::PreOrder, |directory, entry| {
+                if entry.kind() == Some(git2::ObjectType::Blob) {
+                    let file = Path::new(directory).join(entry.name().unwrap_or(""));
+                    if file != path {
+                        paths.push(file);
+                    }
+                }
+                git2::TreeWalkResult::Ok
+            })?;
+        } else if self.copies >= 1 {
+            let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree()?), None)?;
+            for delta in diff.deltas() {
+                if matches!(delta.status(), Delta::Modified | Delta::Deleted)
+                    && let Some(file) = delta.old_file().path()
+                    && file != path
+                {
+                    paths.push(file.to_path_buf());
+                }
+            }
+        }
+
+        Ok(paths)
+    }
+}
+
+/// Lines of a text file at a commit, read once; None for missing and binary files
+fn file_lines<'c>(
+    repo: &Repository,
+    contents: &'c mut HashMap<(Oid, PathBuf), Option<Vec<String>>>,
+    commit_id: Oid,
+    path: &Path,
+) -> Result<Option<&'c Vec<String>>> {
+    let lines = match contents.entry((commit_id, path.to_path_buf())) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let tree = repo.find_commit(commit_id)?.tree()?;
+            let blob = tree
+                .get_path(path)
+                .ok()
+                .and_then(|entry| entry.to_object(repo).ok())
+                .and_then(|object| object.peel_to_blob().ok());
+            let lines = blob.filter(|blob| !blob.is_binary()).map(|blob| {
+                String::from_utf8_lossy(blob.content())
+                    .lines()
+                    .map(str::to_string)
+                    .collect()
+            });
+            entry.insert(lines)
+        }
+    };
+    Ok(lines.as_ref())
+}
+
+/// Blocks of `lines` that also appear in one of the candidate files, as (index in `lines`,
+/// candidate path, line number in the candidate); the longest block wins, earlier files first
+fn find_copies(
+    lines: &[String],
+    candidates: &[(PathBuf, Vec<String>)],
+) -> Vec<(usize, PathBuf, usize)> {
+    let mut positions: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+    for (file, (_, candidate_lines)) in candidates.iter().enumerate() {
+        for (index, line) in candidate_lines.iter().enumerate() {
+            positions
+                .entry(line.as_str())
+                .or_default()
+                .push((file, index));
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut best: Option<(usize, usize, usize)> = None;
+        for &(file, index) in positions.get(lines[start].as_str()).into_iter().flatten() {
+            let candidate_lines = &candidates[file].1;
+            let length = lines[start..]
+                .iter()
+                .zip(&candidate_lines[index..])
+                .take_while(|(line, candidate)| line == candidate)
+                .count();
+            if best.is_none_or(|(best_length, _, _)| length > best_length) {
+                best = Some((length, file, index));
+            }
+        }
+
+        match best {
+            Some((length, file, index))
+                if alphanumeric_count(&lines[start..start + length]) >= MIN_MATCH_CHARACTERS =>
+            {
+                for offset in 0..length {
+                    found.push((
+                        start + offset,
+                        candidates[file].0.clone(),
+                        index + offset + 1,
+                    ));
+                }
+                start += length;
+            }
+            _ => start += 1,
+        }
+    }
+
+    found
+}
+
+fn alphanumeric_count(lines: &[String]) -> usize {
+    lines
+        .iter()
+        .map(|line| line.chars().filter(|c| c.is_alphanumeric()).count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use tempfile::tempdir;
+
+    fn strings(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_find_copies() {
+        let candidates = vec![
+            (
+                PathBuf::from("a.rs"),
+                strings(&["}", "fn parse_arguments() {", "    run_everything();", "}"]),
+            ),
+            (PathBuf::from("b.rs"), strings(&["}", "x"])),
+        ];
+        let lines = strings(&["fn parse_arguments() {", "    run_everything();", "}", "}"]);
+        let found = find_copies(&lines, &candidates);
+        assert_eq!(
+            found
+                .iter()
+                .map(|(index, path, line)| (*index, path.to_str().unwrap(), *line))
+                .collect::<Vec<_>>(),
+            vec![(0, "a.rs", 2), (1, "a.rs", 3), (2, "a.rs", 4)]
+        );
+
+        // A lone brace is too short to count as copied
+        assert!(find_copies(&strings(&["}"]), &candidates).is_empty());
+    }
+
+    #[test]
+    fn test_moves_and_copies() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let helper = "fn helper_function() {\n    compute_the_answer();\n}\n";
+        let original = commit_files(
+            &repo,
+            &[
+                ("a.rs", &format!("fn main() {{}}\n{}", helper)),
+                ("b.rs", "// b\n"),
+            ],
+            "Alice",
+            1749456964,
+            "add files",
+        );
+        // Bob moves the helper from a.rs to b.rs
+        commit_files(
+            &repo,
+            &[
+                ("a.rs", "fn main() {}\n"),
+                ("b.rs", &format!("// b\n{}", helper)),
+            ],
+            "Bob",
+            1749556964,
+            "move helper",
+        );
+
+        let blame = blame_at(&repo, Path::new("b.rs"), None, CopyDetection::default()).unwrap();
+        let plain = CopyDetection::default();
+        assert!(
+            plain
+                .line_sources(&repo, &blame, Path::new("b.rs"))
+                .unwrap()
+                .is_empty()
+        );
+
+        // Only moves within b.rs: the helper came from another file
+        let moves = CopyDetection {
+            moves: true,
+            copies: 0,
+        };
+        assert!(
+            moves
+                .line_sources(&repo, &blame, Path::new("b.rs"))
+                .unwrap()
+                .is_empty()
+        );
+
+        let copies = CopyDetection {
+            moves: true,
+            copies: 1,
+        };
+        let sources = copies
+            .line_sources(&repo, &blame, Path::new("b.rs"))
+            .unwrap();
+        assert_eq!(sources.len(), 3);
+        let source = &sources[&2];
+        assert_eq!(source.commit_id, original);
+        assert_eq!(source.path, Path::new("a.rs"));
+        assert_eq!(source.line, 2);
+        assert_eq!(sources[&4].line, 4);
+    }
+}
@@ -1,45 +1,80 @@
 use anyhow::{Result, anyhow};
-use git2::Repository;
+use git2::{BlameOptions, ObjectType, Oid, Repository};
+use std::collections::HashSet;
+use std::fmt;
 use std::path::{Path, PathBuf};
 
+use crate::copies::{CopyDetection, LineSource};
+use crate::links::{RemoteLinks, hyperlink, terminal_supports_hyperlinks};
+use crate::refs::RefPatterns;
 use crate::syntax::SyntaxHighlighter;
-use crate::utils::{format_timestamp_day_month, format_timestamp_day_month_year};
+use crate::utils::{format_timestamp_day_month, format_timestamp_day_month_year, shown_lines};
 
 /// Color scheme for output formatting
-struct ColorScheme {
-    commit: &'static str,
-    date: &'static str,
-    reset: &'static str,
+pub struct ColorScheme {
+    pub commit: &'static str,
+    pub date: &'static str,
+    pub added: &'static str,
+    pub removed: &'static str,
+    pub reset: &'static str,
+    /// Whether commit hashes are OSC 8 hyperlinks to their web pages
+    pub hyperlinks: bool,
 }
 
 impl ColorScheme {
-    fn new(no_color: bool) -> Self {
+    pub fn new(no_color: bool) -> Self {
         if no_color {
             Self {
                 commit: "",
                 date: "",
+                added: "",
+                removed: "",
                 reset: "",
+                hyperlinks: false,
             }
         } else {
             Self {
-                commit: "\x1b[33m", // Yellow
-                date: "\x1b[36m",   // Cyan
-                reset: "\x1b[0m",   // Reset
+                commit: "\x1b[33m",  // Yellow
+                date: "\x1b[36m",    // Cyan
+                added: "\x1b[32m",   // Green
+                removed: "\x1b[31m", // Red
+                reset: "\x1b[0m",    // Reset
+                hyperlinks: terminal_supports_hyperlinks(),
             }
         }
     }
 }
 
+/// What regular mode adds to a commit: its web page and the pull requests and issues its
+/// message mentions, set up from the repository's remote and config once for all its commits
+pub struct CommitDecorations {
+    pub remote: Option<RemoteLinks>,
+    pub patterns: RefPatterns,
+}
+
+impl CommitDecorations {
+    pub fn from_repo(repo: &Repository) -> Result<Self> {
+        Ok(Self {
+            remote: RemoteLinks::from_repo(repo),
+            patterns: RefPatterns::from_repo(repo)?,
+        })
+    }
+}
+
 /// Extracted commit information
-struct CommitInfo {
-    hash: String,
-    author: String,
-    date: String,
-    message: String,
+pub struct CommitInfo {
+    pub hash: String,
+    pub author: String,
+    pub date: String,
+    pub message: String,
+    /// Web page of the commit, when the repository's hosting service is known
+    pub url: Option<String>,
+    /// Pull requests and issues mentioned in the message, e.g. "#123 PLAT-456"
+    pub refs: String,
 }
 
 impl CommitInfo {
-    fn from_commit(commit: &git2::Commit, use_year: bool) -> Self {
+    pub fn from_commit(commit: &git2::Commit, use_year: bool) -> Self {
         let author = commit.author();
         let name = author.name().unwrap_or("Unknown");
         let time = commit.time();
@@ -53,12 +88,36 @@ impl CommitInfo {
                 format_timestamp_day_month(time.seconds())
             },
             message: commit.summary().unwrap_or("No message").to_string(),
+            url: None,
+            refs: String::new(),
         }
     }
 
-    fn from_hunk(repo: &Repository, hunk: &git2::BlameHunk, use_year: bool) -> Result<Self> {
-        let commit = repo.find_commit(hunk.final_commit_id())?;
-        Ok(Self::from_commit(&commit, use_year))
+    /// Commit details for regular mode, with the commit's web page and the pull requests and
+    /// issues its message mentions
+    pub fn describe(commit: &git2::Commit, decorations: &CommitDecorations) -> Self {
+        Self::from_commit(commit, true).with_details(commit, decorations)
+    }
+
+    fn from_commit_id(
+        repo: &Repository,
+        commit_id: Oid,
+        decorations: &CommitDecorations,
+    ) -> Result<Self> {
+        let commit = repo.find_commit(commit_id)?;
+        Ok(Self::from_commit(&commit, false).with_details(&commit, decorations))
+    }
+
+    fn with_details(mut self, commit: &git2::Commit, decorations: &CommitDecorations) -> Self {
+        self.url = decorations
+            .remote
+            .as_ref()
+            .and_then(|remote| remote.commit_url(commit.id()));
+        self.refs = decorations
+            .patterns
+            .extract(commit.message().unwrap_or(""))
+            .label();
+        self
     }
 
     fn unknown(_use_year: bool) -> Self {
@@ -67,78 +126,98 @@ impl CommitInfo {
             author: "Unknown".to_string(),
             date: "Unknown".to_string(),
             message: "Unknown".to_string(),
+            url: None,
+            refs: String::new(),
+        }
+    }
+
+    /// The short hash, as a terminal hyperlink to the commit page when the terminal supports it
+    fn hash_label(&self, colors: &ColorScheme) -> String {
+        match &self.url {
+            Some(url) if colors.hyperlinks => hyperlink(&self.hash, url),
+            _ => self.hash.clone(),
         }
     }
 
-    /// Format for regular mode output
-    fn format_regular(&self, colors: &ColorScheme, commit_message_separate: bool) -> String {
-        if commit_message_separate {
+    /// Format for regular mode output, followed by the commit's URL with `links`
+    pub fn format_regular(
+        &self,
+        colors: &ColorScheme,
+        commit_message_separate: bool,
+        links: bool,
+    ) -> String {
+        let refs = if self.refs.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", self.refs)
+        };
+        let mut result = if commit_message_separate {
             format!(
-                "{}{}{} {} - {}{}{}\n└─ {}",
+                "{}{}{} {} - {}{}{}{}\n└─ {}",
                 colors.commit,
-                self.hash,
+                self.hash_label(colors),
                 colors.reset,
                 self.author,
                 colors.date,
                 self.date,
                 colors.reset,
+                refs,
                 self.message
             )
         } else {
             format!(
-                "{}{}{} {} - {}{}{}: {}",
+                "{}{}{} {} - {}{}{}{}: {}",
                 colors.commit,
-                self.hash,
+                self.hash_label(colors),
                 colors.reset,
                 self.author,
                 colors.date,
                 self.date,
                 colors.reset,
+                refs,
                 self.message
             )
+        };
+        if links && let Some(url) = &self.url {
+            result.push_str(&format!("\n   {}", url));
         }
+        result
     }
 
-    /// Format for blame mode output
+    /// Format for blame mode output, with the commit message and `extra` details such as the
+    /// line's origin and URL on rows below
     fn format_blame(
         &self,
         colors: &ColorScheme,
         line_num: usize,
         highlighted_line: &str,
         commit_message_separate: bool,
+        extra: &[String],
     ) -> String {
-        if commit_message_separate {
-            format!(
-                "│ {}{:<7}{} │ {:<15} │ {}{:<6}{} │ {:>4} │ {}\n│ {:<7} │ {:<15} │ {:<6} │ {:<4} │ └─ {}\n",
-                colors.commit,
-                self.hash,
-                colors.reset,
-                self.author,
-                colors.date,
-                self.date,
-                colors.reset,
-                line_num,
-                highlighted_line,
-                "",
-                "",
-                "",
-                "",
-                self.message,
-            )
-        } else {
-            format!(
-                "│ {}{:<7}{} │ {:<15} │ {}{:<6}{} │ {:>4} │ {}\n",
-                colors.commit,
-                self.hash,
-                colors.reset,
-                self.author,
-                colors.date,
-                self.date,
-                colors.reset,
-                line_num,
-                highlighted_line,
-            )
+        let mut result = format!(
+            "│ {}{:<7}{} │ {:<15} │ {}{:<6}{} │ {:>4} │ {}\n",
+            colors.commit,
+            self.hash_label(colors),
+            colors.reset,
+            self.author,
+            colors.date,
+            self.date,
+            colors.reset,
+            line_num,
+            highlighted_line,
+        );
+        let details = [
+            commit_message_separate.then_some(self.message.as_str()),
+            Some(self.refs.as_str()).filter(|refs| commit_message_separate && !refs.is_empty()),
+        ];
+        let extra = extra.iter().map(|detail| Some(detail.as_str()));
+        for detail in details.into_iter().chain(extra).flatten() {
+            result.push_str(&format!(
+                "│ {:<7} │ {:<15} │ {:<6} │ {:<4} │ └─ {}\n",
+                "", "", "", "", detail
+            ));
         }
+        result
     }
 
     /// Format date-only for blame mode
@@ -155,11 +234,474 @@ impl CommitInfo {
     }
 }
 
+/// How commits are shown in blame and normal mode
+#[derive(Clone, Copy, Default)]
+pub struct OutputOptions {
+    pub no_color: bool,
+    /// Only the date instead of the full commit
+    pub date_only: bool,
+    /// The commit message, on its own line in blame
+    pub commit_message: bool,
+    /// Links to commits and lines on the remote's web interface
+    pub links: bool,
+    /// Moved and copied lines blame looks through
+    pub copies: CopyDetection,
+}
+
+/// Which commits normal mode reports for a path
+#[derive(Clone, Copy, PartialEq)]
+pub enum Origin {
+    /// The most recent commit(s) that touched the path
+    LastEdit,
+    /// The commit that first added the path, following renames back to its origin
+    Created,
+    /// Both the creating and the last editing commit
+    Both,
+}
+
+/// A path to report on, either on disk or inside a repository's object database
+#[derive(Clone, PartialEq)]
+pub enum TargetPath {
+    /// A file or directory in a working copy, resolved against the filesystem
+    Workdir(String),
+    /// A path inside the repository tree at a revision (HEAD when unset), no working copy needed
+    Tree {
+        git_dir: PathBuf,
+        rev: Option<String>,
+        path: String,
+    },
+}
+
+impl TargetPath {
+    /// The revision the path is read at, from --ref or "REV:path"
+    pub fn rev(&self) -> Option<&str> {
+        match self {
+            TargetPath::Workdir(_) => None,
+            TargetPath::Tree { rev, .. } => rev.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for TargetPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetPath::Workdir(path) => write!(f, "{}", path),
+            TargetPath::Tree {
+                rev: Some(rev),
+                path,
+                ..
+            } => write!(f, "{}:{}", rev, path),
+            TargetPath::Tree { path, .. } => write!(f, "{}", path),
+        }
+    }
+}
+
+/// An opened target: its repository and where to read history and content from
+pub struct Target {
+    pub repo: Repository,
+    /// Location on disk, when content is read from the working copy
+    pub full_path: Option<PathBuf>,
+    /// Path relative to the repository root
+    pub relative_path: PathBuf,
+    /// Commit to start history from and read content at (HEAD when unset)
+    pub start: Option<Oid>,
+    /// Commit that deleted the path, when it no longer exists
+    pub deleted_in: Option<Oid>,
+}
+
+impl Target {
+    pub fn open(target: &TargetPath, must_be_file: bool) -> Result<Self> {
+        match target {
+            TargetPath::Workdir(path) => {
+                // Paths that are gone from disk are looked up in history instead
+                if !std::env::current_dir()?.join(path).exists() {
+                    return validate_deleted_path(path, must_be_file);
+                }
+
+                let (repo, full_path, relative_path) = validate_git_path(path, must_be_file)?;
+                Ok(Self {
+                    repo,
+                    full_path: Some(full_path),
+                    relative_path,
+                    start: None,
+                    deleted_in: None,
+                })
+            }
+            TargetPath::Tree { git_dir, rev, path } => {
+                validate_tree_path(git_dir, rev.as_deref(), path, must_be_file)
+            }
+        }
+    }
+
+    /// Start a revwalk at the target's revision
+    pub fn revwalk(&self) -> Result<git2::Revwalk<'_>> {
+        let mut revwalk = self.repo.revwalk()?;
+        match self.start {
+            Some(oid) => revwalk.push(oid)?,
+            None => revwalk.push_head()?,
+        }
+        Ok(revwalk)
+    }
+
+    /// Read the file content from the working copy, or from the git object at the target's revision
+    pub fn read_content(&self) -> Result<String> {
+        if let (Some(full_path), None) = (&self.full_path, self.start) {
+            return std::fs::read_to_string(full_path)
+                .map_err(|e| anyhow!("Failed to read file: {}", e));
+        }
+        self.committed_content()
+    }
+
+    /// Read the file content from the git object at the target's revision, or HEAD
+    pub fn committed_content(&self) -> Result<String> {
+        let blob = self
+            .start_commit()?
+            .tree()?
+            .get_path(&self.relative_path)?
+            .to_object(&self.repo)?
+            .peel_to_blob()?;
+
+        Ok(String::from_utf8_lossy(blob.content()).to_string())
+    }
+
+    /// If the target's repository is a submodule checkout, find its superproject
+    /// Returns the superproject repository and the submodule's path inside it
+    fn superproject(&self) -> Option<(Repository, PathBuf)> {
+        let workdir = self.repo.workdir()?.canonicalize().ok()?;
+        let super_repo = Repository::discover(workdir.parent()?).ok()?;
+        let super_workdir = super_repo.workdir()?.canonicalize().ok()?;
+        let submodule_path = workdir.strip_prefix(&super_workdir).ok()?.to_path_buf();
+
+        // A nested repository or a linked worktree inside the superproject isn't a submodule
+        let is_submodule = super_repo
+            .submodules()
+            .ok()?
+            .iter()
+            .any(|submodule| submodule.path() == submodule_path);
+
+        is_submodule.then_some((super_repo, submodule_path))
+    }
+
+    /// For a submodule checkout, the submodule's path in its superproject, whose history holds
+    /// the commits that bumped it
+    /// A requested revision (--ref) is looked up in the superproject too, so the bumps are the
+    /// ones on that branch or tag rather than on the superproject's HEAD
+    pub fn superproject_target(&self, rev: Option<&str>) -> Result<Option<Target>> {
+        let Some((repo, relative_path)) = self.superproject() else {
+            return Ok(None);
+        };
+        let start = match rev {
+            Some(rev) => Some(
+                repo.revparse_single(rev)
+                    .and_then(|object| object.peel_to_commit())
+                    .map_err(|_| anyhow!("Cannot resolve revision '{}' in the superproject", rev))?
+                    .id(),
+            ),
+            None => None,
+        };
+        Ok(Some(Target {
+            repo,
+            full_path: None,
+            relative_path,
+            start,
+            deleted_in: None,
+        }))
+    }
+
+    /// The commit the target is read at: its revision, or HEAD
+    pub fn start_commit(&self) -> Result<git2::Commit<'_>> {
+        Ok(match self.start {
+            Some(oid) => self.repo.find_commit(oid)?,
+            None => self.repo.head()?.peel_to_commit()?,
+        })
+    }
+
+    /// Whether the path exists in the tree at HEAD
+    pub fn in_head(&self) -> Result<bool> {
+        Ok(match self.repo.head() {
+            Ok(head) => tree_contains_path(&head.peel_to_tree()?, &self.relative_path),
+            // No commits yet
+            Err(_) => false,
+        })
+    }
+
+    /// Path used to pick a syntax for highlighting
+    pub fn syntax_path(&self) -> &Path {
+        self.full_path.as_deref().unwrap_or(&self.relative_path)
+    }
+}
+
+/// Open the repository given with --git-dir, or the one containing the current directory
+pub fn open_repo(git_dir: Option<&Path>) -> Result<Repository> {
+    match git_dir {
+        Some(git_dir) => Repository::open(git_dir)
+            .map_err(|_| anyhow!("'{}' is not a git repository", git_dir.display())),
+        None => Repository::discover(".").map_err(|_| anyhow!("Not in a git repository")),
+    }
+}
+
+/// Check whether an input should be read from a repository's objects rather than the filesystem
+/// This is the case with --git-dir, --ref, inside a bare repository, or for "REV:path" inputs
+pub fn tree_target(
+    input: &str,
+    git_dir: Option<&Path>,
+    rev: Option<&str>,
+) -> Result<Option<TargetPath>> {
+    let (repo, forced) = match git_dir {
+        Some(git_dir) => (
+            Repository::open(git_dir)
+                .map_err(|_| anyhow!("'{}' is not a git repository", git_dir.display()))?,
+            true,
+        ),
+        None => match Repository::discover(".") {
+            Ok(repo) if repo.is_bare() || rev.is_some() => (repo, true),
+            // Existing paths on disk always win over "REV:path" interpretation
+            Ok(_) if Path::new(input).exists() => return Ok(None),
+            Ok(repo) => (repo, false),
+            Err(_) if rev.is_some() => {
+                return Err(anyhow!("--ref needs to be run inside a git repository"));
+            }
+            Err(_) => return Ok(None),
+        },
+    };
+
+    let git_dir = repo.path().to_path_buf();
+
+    if let Some((rev, path)) = input.split_once(':')
+        && !rev.is_empty()
+        && repo.revparse_single(rev).is_ok()
+    {
+        return Ok(Some(TargetPath::Tree {
+            git_dir,
+            rev: Some(rev.to_string()),
+            path: path.to_string(),
+        }));
+    }
+
+    if let Some(rev) = rev {
+        repo.revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| anyhow!("Cannot resolve revision '{}' to a commit", rev))?;
+    }
+
+    if forced {
+        return Ok(Some(TargetPath::Tree {
+            git_dir,
+            rev: rev.map(str::to_string),
+            path: workdir_relative(&repo, input).unwrap_or_else(|| input.to_string()),
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Convert a path typed relative to the current directory into a path relative to the
+/// repository root, so "--ref" works from subdirectories of a working copy
+/// Bare names that don't exist here are left alone to be searched for in the tree
+fn workdir_relative(repo: &Repository, input: &str) -> Option<String> {
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let current_dir = std::env::current_dir().ok()?.canonicalize().ok()?;
+
+    let is_name = !input.contains('/') && input != "." && input != "..";
+    if is_name && !current_dir.join(input).exists() {
+        return None;
+    }
+
+    // Resolve "." and ".." lexically, the path may not exist in the working copy
+    let mut full_path = PathBuf::new();
+    for component in current_dir.join(input).components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                full_path.pop();
+            }
+            component => full_path.push(component),
+        }
+    }
+
+    let relative_path = full_path.strip_prefix(&workdir).ok()?;
+    Some(relative_path.to_string_lossy().to_string())
+}
+
+/// Resolves a path inside the repository tree at a revision, checking that it exists there
+fn validate_tree_path(
+    git_dir: &Path,
+    rev: Option<&str>,
+    path: &str,
+    must_be_file: bool,
+) -> Result<Target> {
+    let repo = Repository::open(git_dir)
+        .map_err(|_| anyhow!("'{}' is not a git repository", git_dir.display()))?;
+
+    let rev_name = rev.unwrap_or("HEAD");
+    let relative_path = normalize_tree_path(path);
+
+    let (start, is_dir) = {
+        let commit = repo
+            .revparse_single(rev_name)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| anyhow!("Cannot resolve revision '{}' to a commit", rev_name))?;
+
+        let is_dir = if relative_path.as_os_str().is_empty() {
+            true
+        } else {
+            let entry = commit.tree()?.get_path(&relative_path).map_err(|_| {
+                anyhow!(
+                    "Path '{}' doesn't exist at '{}'. Check spelling.",
+                    path,
+                    rev_name
+                )
+            })?;
+            entry.kind() == Some(ObjectType::Tree)
+        };
+
+        (commit.id(), is_dir)
+    };
+
+    if must_be_file && is_dir {
+        return Err(anyhow!(
+            "Blame can only be used on files, not directories: {}",
+            path
+        ));
+    }
+
+    Ok(Target {
+        repo,
+        full_path: None,
+        relative_path,
+        start: Some(start),
+        deleted_in: None,
+    })
+}
+
+/// Resolves a path that no longer exists on disk by finding it in the repository's history
+/// The target is read as it was just before the commit that deleted it
+fn validate_deleted_path(path: &str, must_be_file: bool) -> Result<Target> {
+    let (repo, relative_path) = missing_path_in_repo(path)?;
+
+    let (start, deleted_in, is_dir) = {
+        let head = repo.head()?.peel_to_commit()?;
+
+        // Deleted in the working copy only: the committed version is still at HEAD
+        let (start, deleted_in) = if head.tree()?.get_path(&relative_path).is_ok() {
+            (head.id(), None)
+        } else {
+            let (deletion, parent) = find_deletion(&repo, &relative_path)?.ok_or_else(|| {
+                anyhow!(
+                    "Path '{}' doesn't exist and was never tracked in this repository. Check spelling.",
+                    path
+                )
+            })?;
+            (parent, Some(deletion))
+        };
+
+        let entry = repo.find_commit(start)?.tree()?.get_path(&relative_path)?;
+        (start, deleted_in, entry.kind() == Some(ObjectType::Tree))
+    };
+
+    if must_be_file && is_dir {
+        return Err(anyhow!(
+            "Blame can only be used on files, not directories: {}",
+            path
+        ));
+    }
+
+    Ok(Target {
+        repo,
+        full_path: None,
+        relative_path,
+        start: Some(start),
+        deleted_in,
+    })
+}
+
+/// The repository a path that isn't on disk belongs to, and the path relative to its root
+fn missing_path_in_repo(path: &str) -> Result<(Repository, PathBuf)> {
+    let absolute = std::env::current_dir()?.join(path);
+
+    // Find the nearest existing ancestor to discover the repository from
+    let mut existing = absolute.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        missing.push(existing.file_name().unwrap_or_default());
+        existing = existing
+            .parent()
+            .ok_or_else(|| anyhow!("Path '{}' doesn't exist. Check spelling.", path))?;
+    }
+    let full_path = missing
+        .iter()
+        .rev()
+        .fold(existing.canonicalize()?, |full_path, component| {
+            full_path.join(component)
+        });
+
+    let repo = Repository::discover(existing).map_err(|_| {
+        anyhow!(
+            "Path '{}' doesn't exist and is not in a git repository. Check spelling.",
+            path
+        )
+    })?;
+    let repo_workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?
+        .canonicalize()?;
+    let relative_path = full_path
+        .strip_prefix(&repo_workdir)
+        .map_err(|_| {
+            anyhow!(
+                "Path '{}' is not within the repository at '{}'",
+                path,
+                repo_workdir.display()
+            )
+        })?
+        .to_path_buf();
+
+    Ok((repo, relative_path))
+}
+
+/// Find the most recent commit that deleted a path, walking back from HEAD
+/// Returns the deleting commit and its first parent, which still had the path
+pub fn find_deletion(repo: &Repository, path: &Path) -> Result<Option<(Oid, Oid)>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        if commit.tree()?.get_path(path).is_ok() {
+            continue;
+        }
+
+        // A merge whose first parent already lacks the path only brought in a branch that still
+        // had it; the deletion happened on the first-parent side
+        if let Ok(parent) = commit.parent(0)
+            && parent.tree()?.get_path(path).is_ok()
+        {
+            return Ok(Some((commit.id(), parent.id())));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Normalize a repository tree path: strip "./" prefixes and trailing slashes, "." is the root
+pub fn normalize_tree_path(path: &str) -> PathBuf {
+    let mut path = path.trim_end_matches('/');
+    while let Some(rest) = path.strip_prefix("./") {
+        path = rest;
+    }
+    if path == "." {
+        path = "";
+    }
+    PathBuf::from(path)
+}
+
 /// Validates path existence, file type, finds git repository, and returns all necessary paths
 fn validate_git_path(path: &str, must_be_file: bool) -> Result<(Repository, PathBuf, PathBuf)> {
-    // First, resolve the full path
+    // First, resolve the full path (canonicalized so symlinked locations match the repo workdir)
     let full_path = if Path::new(path).is_absolute() {
-        PathBuf::from(path)
+        Path::new(path)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(path))
     } else {
         // For relative paths, resolve them against current working directory
         std::env::current_dir()?
@@ -210,33 +752,61 @@ fn validate_git_path(path: &str, must_be_file: bool) -> Result<(Repository, Path
     Ok((repo, full_path, relative_path))
 }
 
+/// Blame a file as of a commit (HEAD when unset)
+pub fn blame_at<'r>(
+    repo: &'r Repository,
+    path: &Path,
+    start: Option<Oid>,
+    copies: CopyDetection,
+) -> Result<git2::Blame<'r>, git2::Error> {
+    let mut blame_options = BlameOptions::new();
+    if let Some(start) = start {
+        blame_options.newest_commit(start);
+    }
+    copies.configure(&mut blame_options);
+
+    repo.blame_file(path, Some(&mut blame_options))
+}
+
 pub fn get_blame(
-    path: &str,
-    no_color: bool,
-    date_only: bool,
-    commit_message: bool,
+    path: &TargetPath,
+    options: OutputOptions,
+    line_range: Option<(usize, usize)>,
+    changed_lines: Option<&HashSet<usize>>,
 ) -> Result<String> {
+    let OutputOptions {
+        no_color,
+        date_only,
+        commit_message,
+        links,
+        copies,
+    } = options;
+
     // Validate path and get repository, full path, and relative path
-    let (repo, full_path, relative_path) = validate_git_path(path, true)?;
+    let target = Target::open(path, true)?;
+    let repo = &target.repo;
 
     // Get the blame for the file
-    let blame = repo
-        .blame_file(&relative_path, None)
-        .map_err(|e| match e.code() {
-            git2::ErrorCode::NotFound => anyhow!(
-                "File '{}' exists but is not tracked by git. Use 'git add {}' to track it first.",
-                path,
-                path
-            ),
-            _ => anyhow!("Failed to get blame for file '{}': {}", path, e),
-        })?;
+    let blame = blame_at(repo, &target.relative_path, target.start, copies).map_err(|e| match e
+        .code()
+    {
+        git2::ErrorCode::NotFound => anyhow!(
+            "File '{}' exists but is not tracked by git. Use 'git add {}' to track it first.",
+            path,
+            path
+        ),
+        _ => anyhow!("Failed to get blame for file '{}': {}", path, e),
+    })?;
+
+    let sources = copies.line_sources(repo, &blame, &target.relative_path)?;
 
     // Read the file content to display alongside blame
-    let file_content =
-        std::fs::read_to_string(&full_path).map_err(|e| anyhow!("Failed to read file: {}", e))?;
+    let file_content = target.read_content()?;
 
     let lines: Vec<&str> = file_content.lines().collect();
 
+    let (first_line, last_line) = shown_lines(path, line_range, lines.len())?;
+
     // Initialize syntax highlighter if colors are enabled
     let highlighter = if !no_color {
         Some(SyntaxHighlighter::new())
@@ -245,6 +815,7 @@ pub fn get_blame(
     };
 
     let colors = ColorScheme::new(no_color);
+    let decorations = CommitDecorations::from_repo(repo)?;
     let mut result = String::new();
 
     if date_only {
@@ -281,24 +852,57 @@ pub fn get_blame(
     }
 
     for (line_num, line_content) in lines.iter().enumerate() {
+        // Skip lines outside the requested range (-L)
+        if line_num + 1 < first_line || line_num + 1 > last_line {
+            continue;
+        }
+
         let hunk_result = blame.get_line(line_num + 1);
 
         // Apply syntax highlighting to the line content if enabled
         let highlighted_line = if let Some(ref highlighter) = highlighter {
             highlighter
-                .highlight_line(line_content, &full_path, line_num + 1)
+                .highlight_line(line_content, target.syntax_path(), line_num + 1)
                 .unwrap_or_else(|_| line_content.to_string())
         } else {
             line_content.to_string()
         };
 
         let line_output = if let Some(hunk) = &hunk_result {
-            let commit_info = CommitInfo::from_hunk(&repo, hunk, false)?;
+            // Moved and copied lines are attributed to the commit they came from
+            let source = sources.get(&(line_num + 1));
+            let (commit_id, orig_path, orig_line) = match source {
+                Some(LineSource {
+                    commit_id,
+                    path,
+                    line,
+                }) => (*commit_id, path.as_path(), *line),
+                None => (
+                    hunk.final_commit_id(),
+                    hunk.path().unwrap_or(&target.relative_path),
+                    hunk.orig_start_line() + line_num + 1 - hunk.final_start_line(),
+                ),
+            };
+            let commit_info = CommitInfo::from_commit_id(repo, commit_id, &decorations)?;
 
             if date_only {
                 commit_info.format_date_only(&colors, line_num + 1, &highlighted_line)
             } else {
-                commit_info.format_blame(&colors, line_num + 1, &highlighted_line, commit_message)
+                let origin = source.map(|_| format!("from {}:{}", orig_path.display(), orig_line));
+                // Link to the line as it was in the commit, which may be under another name
+                let line_url = decorations
+                    .remote
+                    .as_ref()
+                    .filter(|_| links)
+                    .and_then(|remote| remote.file_url(commit_id, orig_path, orig_line));
+                let extra: Vec<String> = origin.into_iter().chain(line_url).collect();
+                commit_info.format_blame(
+                    &colors,
+                    line_num + 1,
+                    &highlighted_line,
+                    commit_message,
+                    &extra,
+                )
             }
         } else {
             let commit_info = CommitInfo::unknown(false);
@@ -306,10 +910,29 @@ pub fn get_blame(
             if date_only {
                 commit_info.format_date_only(&colors, line_num + 1, &highlighted_line)
             } else {
-                commit_info.format_blame(&colors, line_num + 1, &highlighted_line, commit_message)
+                commit_info.format_blame(
+                    &colors,
+                    line_num + 1,
+                    &highlighted_line,
+                    commit_message,
+                    &[],
+                )
             }
         };
 
+        // Lines whose attribution changed (--watch) get a marker in place of the left border
+        if changed_lines.is_some_and(|changed| changed.contains(&(line_num + 1))) {
+            for row in line_output.lines() {
+                result.push_str(&format!(
+                    "{}┃{}{}\n",
+                    colors.added,
+                    colors.reset,
+                    row.strip_prefix('│').unwrap_or(row)
+                ));
+            }
+            continue;
+        }
+
         result.push_str(&line_output);
     }
 
@@ -330,47 +953,429 @@ pub fn get_blame(
 }
 
 pub fn get_last_commit(
-    path: &str,
+    path: &TargetPath,
+    options: OutputOptions,
+    last: Option<usize>,
+    recurse_submodules: bool,
+    origin: Origin,
+) -> Result<String> {
+    // Validate path and get repository and relative path (no file requirement for last commit)
+    let target = Target::open(path, false)?;
+    let describe = |target: &Target, decorations: &CommitDecorations| {
+        format_origin(target, path, decorations, options, last, origin)
+    };
+
+    // Inside a submodule, the interesting commit is the superproject one that bumped the pointer
+    if let Some(super_target) = target.superproject_target(path.rev())? {
+        let super_decorations = CommitDecorations::from_repo(&super_target.repo)?;
+        let mut result = format!(
+            "Submodule '{}' last bumped in superproject:\n{}",
+            super_target.relative_path.display(),
+            describe(&super_target, &super_decorations)?
+        );
+
+        if recurse_submodules {
+            let decorations = CommitDecorations::from_repo(&target.repo)?;
+            result.push_str(&format!(
+                "\nInside submodule:\n{}",
+                describe(&target, &decorations)?
+            ));
+        }
+
+        return Ok(result);
+    }
+
+    let decorations = CommitDecorations::from_repo(&target.repo)?;
+
+    // For deleted paths, report who deleted it and who last edited it before that
+    if let Some(deletion) = target.deleted_in {
+        let colors = ColorScheme::new(options.no_color);
+        let commit_info = CommitInfo::describe(&target.repo.find_commit(deletion)?, &decorations);
+        let deleted = if options.date_only {
+            format!("{}{}{}", colors.date, commit_info.date, colors.reset)
+        } else {
+            commit_info.format_regular(&colors, options.commit_message, options.links)
+        };
+
+        let before = match origin {
+            Origin::LastEdit => "Last edited before deletion:\n",
+            Origin::Created => "Created:\n",
+            Origin::Both => "",
+        };
+        return Ok(format!(
+            "Deleted in:\n{}\n{}{}",
+            deleted,
+            before,
+            describe(&target, &decorations)?
+        ));
+    }
+
+    // Uncommitted changes come first, they are newer than any commit
+    let Some(status) = working_tree_status(&target)? else {
+        return describe(&target, &decorations);
+    };
+    let colors = ColorScheme::new(options.no_color);
+    let status = format!("{}{}{}", colors.removed, status.describe(), colors.reset);
+
+    // Untracked paths have no history yet, the status is all there is to say
+    if !target.in_head()? && last_commits(&target, Some(1))?.is_empty() {
+        return Ok(status);
+    }
+    Ok(format!("{}\n{}", status, describe(&target, &decorations)?))
+}
+
+/// Uncommitted changes to a path in the working copy
+pub enum WorkingTreeStatus {
+    Ignored,
+    Untracked,
+    StagedAndModified,
+    Staged,
+    Modified,
+    /// Counts of dirty files in a directory
+    Directory {
+        modified: usize,
+        staged: usize,
+        untracked: usize,
+    },
+}
+
+impl WorkingTreeStatus {
+    /// Short form for tables, e.g. "staged, modified" or "2 modified, 1 untracked"
+    pub fn label(&self) -> String {
+        match self {
+            WorkingTreeStatus::Ignored => "ignored".to_string(),
+            WorkingTreeStatus::Untracked => "untracked".to_string(),
+            WorkingTreeStatus::StagedAndModified => "staged, modified".to_string(),
+            WorkingTreeStatus::Staged => "staged".to_string(),
+            WorkingTreeStatus::Modified => "modified".to_string(),
+            WorkingTreeStatus::Directory {
+                modified,
+                staged,
+                untracked,
+            } => [
+                (modified, "modified"),
+                (staged, "staged"),
+                (untracked, "untracked"),
+            ]
+            .iter()
+            .filter(|(count, _)| **count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect::<Vec<_>>()
+            .join(", "),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            WorkingTreeStatus::Ignored => {
+                "Ignored (matched by .gitignore, never committed)".to_string()
+            }
+            WorkingTreeStatus::Untracked => "Untracked (not committed yet)".to_string(),
+            WorkingTreeStatus::StagedAndModified => {
+                "Staged, with further unstaged modifications".to_string()
+            }
+            WorkingTreeStatus::Staged => "Staged changes (not committed yet)".to_string(),
+            WorkingTreeStatus::Modified => {
+                "Modified in working tree (not committed yet)".to_string()
+            }
+            WorkingTreeStatus::Directory { .. } => format!("Uncommitted changes: {}", self.label()),
+        }
+    }
+}
+
+/// Uncommitted changes to a path in the working copy: a status for a file, or counts of dirty
+/// files for a directory. None when the path is clean
+pub fn working_tree_status(target: &Target) -> Result<Option<WorkingTreeStatus>> {
+    // Only paths read from the working copy can have uncommitted changes
+    let (Some(full_path), None) = (&target.full_path, target.start) else {
+        return Ok(None);
+    };
+    let repo = &target.repo;
+
+    if full_path.is_dir() {
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        if !target.relative_path.as_os_str().is_empty() {
+            options.pathspec(&target.relative_path);
+        }
+
+        let (mut modified, mut staged, mut untracked) = (0, 0, 0);
+        for entry in repo.statuses(Some(&mut options))?.iter() {
+            let status = entry.status();
+            if status.is_wt_new() {
+                untracked += 1;
+                continue;
+            }
+            if status.intersects(
+                git2::Status::INDEX_NEW
+                    | git2::Status::INDEX_MODIFIED
+                    | git2::Status::INDEX_DELETED
+                    | git2::Status::INDEX_RENAMED
+                    | git2::Status::INDEX_TYPECHANGE,
+            ) {
+                staged += 1;
+            }
+            if status.intersects(
+                git2::Status::WT_MODIFIED
+                    | git2::Status::WT_DELETED
+                    | git2::Status::WT_RENAMED
+                    | git2::Status::WT_TYPECHANGE,
+            ) {
+                modified += 1;
+            }
+        }
+
+        if modified + staged + untracked == 0 {
+            return Ok(None);
+        }
+        return Ok(Some(WorkingTreeStatus::Directory {
+            modified,
+            staged,
+            untracked,
+        }));
+    }
+
+    let status = repo.status_file(&target.relative_path)?;
+    let staged = status.intersects(
+        git2::Status::INDEX_NEW
+            | git2::Status::INDEX_MODIFIED
+            | git2::Status::INDEX_RENAMED
+            | git2::Status::INDEX_TYPECHANGE,
+    );
+    let modified = status.intersects(
+        git2::Status::WT_MODIFIED | git2::Status::WT_RENAMED | git2::Status::WT_TYPECHANGE,
+    );
+
+    Ok(if status.is_ignored() {
+        Some(WorkingTreeStatus::Ignored)
+    } else if status.is_wt_new() {
+        Some(WorkingTreeStatus::Untracked)
+    } else if staged && modified {
+        Some(WorkingTreeStatus::StagedAndModified)
+    } else if staged {
+        Some(WorkingTreeStatus::Staged)
+    } else if modified {
+        Some(WorkingTreeStatus::Modified)
+    } else {
+        None
+    })
+}
+
+/// Find the most recent commit touching a path on any branch, remote-tracking branch or tag
+/// Reports the commit and the branches that contain it
+/// The path only has to exist on some ref, not on disk or at HEAD
+pub fn get_last_commit_all_branches(
+    path: &TargetPath,
     no_color: bool,
     date_only: bool,
     commit_message: bool,
-    last: Option<usize>,
+    links: bool,
 ) -> Result<String> {
-    // Validate path and get repository and relative path (no file requirement for last commit)
-    let (repo, _, relative_path) = validate_git_path(path, false)?;
+    let (repo, relative_path) = match path {
+        TargetPath::Workdir(input) if !std::env::current_dir()?.join(input).exists() => {
+            missing_path_in_repo(input)?
+        }
+        TargetPath::Workdir(_) => {
+            let target = Target::open(path, false)?;
+            (target.repo, target.relative_path)
+        }
+        TargetPath::Tree { git_dir, path, .. } => (
+            Repository::open(git_dir)
+                .map_err(|_| anyhow!("'{}' is not a git repository", git_dir.display()))?,
+            normalize_tree_path(path),
+        ),
+    };
+    let repo = &repo;
+    let colors = ColorScheme::new(no_color);
 
     let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    for glob in ["refs/heads", "refs/remotes", "refs/tags"] {
+        revwalk.push_glob(glob)?;
+    }
+    // A detached HEAD isn't on any ref
+    if let Ok(head) = repo.head()
+        && let Some(oid) = head.target()
+    {
+        revwalk.push(oid)?;
+    }
 
-    let colors = ColorScheme::new(no_color);
+    let mut found = None;
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        if commit_touches_path(repo, &commit, &relative_path)? {
+            found = Some(commit);
+            break;
+        }
+    }
+    let commit = found.ok_or_else(|| anyhow!("No commits found for path: {}", path))?;
 
-    // If last is requested, collect multiple contributors
-    if let Some(n) = last {
-        let mut contributors = Vec::new();
-        let mut seen_authors = std::collections::HashSet::new();
+    let mut branches = Vec::new();
+    for branch in repo.branches(None)? {
+        let (branch, _) = branch?;
+        // Symbolic refs like "origin/HEAD" just repeat another branch
+        let (Some(name), Some(tip)) = (branch.name()?, branch.get().target()) else {
+            continue;
+        };
+        if tip == commit.id() || repo.graph_descendant_of(tip, commit.id())? {
+            branches.push(name.to_string());
+        }
+    }
+
+    let commit_info = CommitInfo::describe(&commit, &CommitDecorations::from_repo(repo)?);
+    let mut result = if date_only {
+        format!("{}{}{}", colors.date, commit_info.date, colors.reset)
+    } else {
+        commit_info.format_regular(&colors, commit_message, links)
+    };
+
+    result.push_str(&match branches.len() {
+        0 => "\nNot on any branch (reachable from a tag or HEAD only)".to_string(),
+        1 => format!("\nOn branch: {}", branches[0]),
+        _ => format!("\nOn branches: {}", branches.join(", ")),
+    });
 
-        for commit_id in revwalk {
-            let commit_id = commit_id?;
-            let commit = repo.find_commit(commit_id)?;
+    Ok(result)
+}
 
-            if commit_touches_path(&repo, &commit, &relative_path)? {
-                let commit_info = CommitInfo::from_commit(&commit, true);
+/// Format the commits requested by `origin`: the last edit, the creation, or both
+fn format_origin(
+    target: &Target,
+    path: &TargetPath,
+    decorations: &CommitDecorations,
+    options: OutputOptions,
+    last: Option<usize>,
+    origin: Origin,
+) -> Result<String> {
+    let creation = || format_creation(target, path, decorations, options);
+    let last_edits = || format_last_commits(target, path, decorations, options, last);
+    match origin {
+        Origin::LastEdit => last_edits(),
+        Origin::Created => creation(),
+        Origin::Both => Ok(format!(
+            "Created:\n{}\nLast edited:\n{}",
+            creation()?,
+            last_edits()?
+        )),
+    }
+}
 
-                // Only add if we haven't seen this author before
-                if !seen_authors.contains(&commit_info.author) {
-                    seen_authors.insert(commit_info.author.clone());
-                    contributors.push(commit_info.format_regular(&colors, commit_message));
+/// Format the commit that introduced the target's path, noting its original path if renamed
+fn format_creation(
+    target: &Target,
+    path: &TargetPath,
+    decorations: &CommitDecorations,
+    options: OutputOptions,
+) -> Result<String> {
+    let (commit_id, original_path) =
+        find_creation(target)?.ok_or_else(|| anyhow!("No commits found for path: {}", path))?;
 
-                    // Stop when we have enough contributors
-                    if contributors.len() >= n {
-                        break;
-                    }
-                }
+    let colors = ColorScheme::new(options.no_color);
+    let commit_info = CommitInfo::describe(&target.repo.find_commit(commit_id)?, decorations);
+    let mut result = if options.date_only {
+        format!("{}{}{}", colors.date, commit_info.date, colors.reset)
+    } else {
+        commit_info.format_regular(&colors, options.commit_message, options.links)
+    };
+
+    if original_path != target.relative_path {
+        result.push_str(&format!(
+            "\nOriginally created as '{}'",
+            original_path.display()
+        ));
+    }
+
+    Ok(result)
+}
+
+/// Find the commit that introduced the target's path, following file renames back to the origin
+/// Returns the commit and the path the file had when it was created
+fn find_creation(target: &Target) -> Result<Option<(Oid, PathBuf)>> {
+    let repo = &target.repo;
+    let mut current_path = target.relative_path.clone();
+
+    // The repository root was created by the oldest root commit
+    if current_path.as_os_str().is_empty() {
+        let mut oldest: Option<git2::Commit> = None;
+        for commit_id in target.revwalk()? {
+            let commit = repo.find_commit(commit_id?)?;
+            if commit.parent_count() == 0
+                && oldest
+                    .as_ref()
+                    .is_none_or(|oldest| commit.time().seconds() < oldest.time().seconds())
+            {
+                oldest = Some(commit);
             }
         }
+        return Ok(oldest.map(|commit| (commit.id(), current_path)));
+    }
+
+    for commit_id in target.revwalk()? {
+        let commit = repo.find_commit(commit_id?)?;
+
+        let tree = commit.tree()?;
+        let Ok(entry) = tree.get_path(&current_path) else {
+            continue;
+        };
+
+        let mut parent_trees = Vec::new();
+        for parent in commit.parents() {
+            parent_trees.push(parent.tree()?);
+        }
+        if parent_trees
+            .iter()
+            .any(|parent_tree| parent_tree.get_path(&current_path).is_ok())
+        {
+            continue;
+        }
+
+        // The path appears in this commit; for files, check whether it was renamed from elsewhere
+        let renamed_from = match (entry.kind(), parent_trees.first()) {
+            (Some(ObjectType::Blob), Some(parent_tree)) => {
+                let mut diff = repo.diff_tree_to_tree(Some(parent_tree), Some(&tree), None)?;
+                diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+                diff.deltas()
+                    .find(|delta| {
+                        delta.status() == git2::Delta::Renamed
+                            && delta.new_file().path() == Some(current_path.as_path())
+                    })
+                    .and_then(|delta| delta.old_file().path().map(Path::to_path_buf))
+            }
+            _ => None,
+        };
+
+        match renamed_from {
+            Some(old_path) => current_path = old_path,
+            None => return Ok(Some((commit.id(), current_path))),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Format the last commit (or last N contributors) that touched the target
+fn format_last_commits(
+    target: &Target,
+    path: &TargetPath,
+    decorations: &CommitDecorations,
+    options: OutputOptions,
+    last: Option<usize>,
+) -> Result<String> {
+    let colors = ColorScheme::new(options.no_color);
+    let commits = last_commits(target, last)?;
+    if commits.is_empty() {
+        return Err(anyhow!("No commits found for path: {}", path));
+    }
 
-        if contributors.is_empty() {
-            return Err(anyhow!("No commits found for path: {}", path));
+    // If last is requested, list multiple contributors
+    if let Some(n) = last {
+        let mut contributors = Vec::new();
+        for commit in &commits {
+            contributors.push(CommitInfo::describe(commit, decorations).format_regular(
+                &colors,
+                options.commit_message,
+                options.links,
+            ));
         }
 
         let mut result = contributors.join("\n");
@@ -387,31 +1392,64 @@ pub fn get_last_commit(
         return Ok(result);
     }
 
-    // Original single commit logic
-    for commit_id in revwalk {
-        let commit_id = commit_id?;
-        let commit = repo.find_commit(commit_id)?;
+    let commit_info = CommitInfo::describe(&commits[0], decorations);
 
-        if commit_touches_path(&repo, &commit, &relative_path)? {
-            let commit_info = CommitInfo::from_commit(&commit, true);
+    // If date_only is requested, return just the date
+    if options.date_only {
+        return Ok(if options.no_color {
+            commit_info.date
+        } else {
+            format!("{}{}{}", colors.date, commit_info.date, colors.reset)
+        });
+    }
 
-            // If date_only is requested, return just the date
-            if date_only {
-                return Ok(if no_color {
-                    commit_info.date
-                } else {
-                    format!("{}{}{}", colors.date, commit_info.date, colors.reset)
-                });
-            }
+    Ok(commit_info.format_regular(&colors, options.commit_message, options.links))
+}
+
+/// The newest commit that touched the target, or with `last` the newest commit of each of
+/// the last `last` distinct authors; empty when no commit touched it
+pub fn last_commits(target: &Target, last: Option<usize>) -> Result<Vec<git2::Commit<'_>>> {
+    let (repo, relative_path) = (&target.repo, &target.relative_path);
+    let wanted = last.unwrap_or(1);
+
+    let mut commits = Vec::new();
+    let mut seen_authors = std::collections::HashSet::new();
+    for commit_id in target.revwalk()? {
+        let commit = repo.find_commit(commit_id?)?;
+        if !commit_touches_path(repo, &commit, relative_path)? {
+            continue;
+        }
 
-            return Ok(commit_info.format_regular(&colors, commit_message));
+        // Only add if we haven't seen this author before
+        let author = commit.author().name().unwrap_or("Unknown").to_string();
+        if seen_authors.insert(author) {
+            commits.push(commit);
+            if commits.len() >= wanted {
+                break;
+            }
         }
     }
 
-    Err(anyhow!("No commits found for path: {}", path))
+    Ok(commits)
 }
 
-fn commit_touches_path(repo: &Repository, commit: &git2::Commit, path: &Path) -> Result<bool> {
+/// Diff a commit against its first parent, or against an empty tree for root commits
+pub fn first_parent_diff<'r>(
+    repo: &'r Repository,
+    commit: &git2::Commit,
+    options: Option<&mut git2::DiffOptions>,
+) -> Result<git2::Diff<'r>> {
+    let tree = commit.tree()?;
+    let parent_tree = commit
+        .parents()
+        .next()
+        .map(|parent| parent.tree())
+        .transpose()?;
+
+    Ok(repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), options)?)
+}
+
+pub fn commit_touches_path(repo: &Repository, commit: &git2::Commit, path: &Path) -> Result<bool> {
     // For the first commit (no parents), check if the path exists in the tree
     if commit.parent_count() == 0 {
         let tree = commit.tree()?;
@@ -419,24 +1457,20 @@ fn commit_touches_path(repo: &Repository, commit: &git2::Commit, path: &Path) ->
     }
 
     // For commits with parents, check if the path was modified
-    let tree = commit.tree()?;
-    let parent = commit.parent(0)?;
-    let parent_tree = parent.tree()?;
-
-    let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), None)?;
+    let diff = first_parent_diff(repo, commit, None)?;
 
     let mut path_modified = false;
     diff.foreach(
         &mut |delta, _progress| {
-            if let Some(file_path) = delta.new_file().path() {
-                if file_path.starts_with(path) || path.starts_with(file_path) {
-                    path_modified = true;
-                }
+            if let Some(file_path) = delta.new_file().path()
+                && (file_path.starts_with(path) || path.starts_with(file_path))
+            {
+                path_modified = true;
             }
-            if let Some(file_path) = delta.old_file().path() {
-                if file_path.starts_with(path) || path.starts_with(file_path) {
-                    path_modified = true;
-                }
+            if let Some(file_path) = delta.old_file().path()
+                && (file_path.starts_with(path) || path.starts_with(file_path))
+            {
+                path_modified = true;
             }
             true
         },
@@ -455,3 +1489,738 @@ fn tree_contains_path(tree: &git2::Tree, path: &Path) -> bool {
 
     tree.get_path(path).is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{commit_files, commit_index, commit_merge, remove_files, stage_files};
+    use tempfile::tempdir;
+
+    fn workdir_target(path: &Path) -> TargetPath {
+        TargetPath::Workdir(path.to_string_lossy().to_string())
+    }
+
+    /// The last commit of a path without colors, as the default mode prints it
+    fn plain_last_commit(path: &TargetPath) -> Result<String> {
+        plain_origin(path, Origin::LastEdit)
+    }
+
+    fn plain_origin(path: &TargetPath, origin: Origin) -> Result<String> {
+        let options = OutputOptions {
+            no_color: true,
+            ..Default::default()
+        };
+        get_last_commit(path, options, None, false, origin)
+    }
+
+    #[test]
+    fn test_linked_worktree_resolves_to_worktree() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("main")).unwrap();
+        commit_files(
+            &repo,
+            &[("src/lib.rs", "one\n")],
+            "Alice",
+            1749456964,
+            "add lib",
+        );
+
+        // Linked worktree placed inside the main checkout, like "git worktree add .worktrees/feature"
+        let worktree_path = dir.path().join("main/.worktrees/feature");
+        std::fs::create_dir_all(worktree_path.parent().unwrap()).unwrap();
+        repo.worktree("feature", &worktree_path, None).unwrap();
+
+        let worktree_repo = Repository::open(&worktree_path).unwrap();
+        commit_files(
+            &worktree_repo,
+            &[("src/lib.rs", "one\ntwo\n")],
+            "Bob",
+            1749556964,
+            "work on feature",
+        );
+
+        let file = workdir_target(&worktree_path.join("src/lib.rs"));
+        let last = plain_last_commit(&file).unwrap();
+        assert!(
+            last.ends_with("Bob - 10 Jun 2025: work on feature"),
+            "{}",
+            last
+        );
+
+        let blame = get_blame(
+            &file,
+            OutputOptions {
+                no_color: true,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(blame.contains("│    2 │ two"), "{}", blame);
+
+        // The main checkout still sees its own branch
+        let main_file = workdir_target(&dir.path().join("main/src/lib.rs"));
+        let last = plain_last_commit(&main_file).unwrap();
+        assert!(last.ends_with("Alice - 09 Jun 2025: add lib"), "{}", last);
+    }
+
+    #[test]
+    fn test_submodule_reports_superproject_bump() {
+        let dir = tempdir().unwrap();
+        let library = Repository::init(dir.path().join("library")).unwrap();
+        commit_files(
+            &library,
+            &[("lib.rs", "one\n")],
+            "Carol",
+            1749456964,
+            "library code",
+        );
+
+        let app = Repository::init(dir.path().join("app")).unwrap();
+        commit_files(
+            &app,
+            &[("README.md", "app\n")],
+            "Alice",
+            1749456964,
+            "init app",
+        );
+
+        let library_url = dir.path().join("library").to_string_lossy().to_string();
+        let mut submodule = app
+            .submodule(&library_url, Path::new("vendor/library"), true)
+            .unwrap();
+        submodule.clone(None).unwrap();
+        submodule.add_finalize().unwrap();
+        commit_index(&app, "Bob", 1749556964, "add library submodule");
+
+        let file = workdir_target(&dir.path().join("app/vendor/library/lib.rs"));
+
+        let last = plain_last_commit(&file).unwrap();
+        assert!(
+            last.starts_with("Submodule 'vendor/library' last bumped in superproject:"),
+            "{}",
+            last
+        );
+        assert!(
+            last.contains("Bob - 10 Jun 2025: add library submodule"),
+            "{}",
+            last
+        );
+        assert!(!last.contains("Carol"), "{}", last);
+
+        let recursed = get_last_commit(
+            &file,
+            OutputOptions {
+                no_color: true,
+                ..Default::default()
+            },
+            None,
+            true,
+            Origin::LastEdit,
+        )
+        .unwrap();
+        assert!(recursed.contains("Inside submodule:"), "{}", recursed);
+        assert!(
+            recursed.contains("Carol - 09 Jun 2025: library code"),
+            "{}",
+            recursed
+        );
+
+        // Bump the submodule again after branching off a release
+        let bump = app.head().unwrap().peel_to_commit().unwrap();
+        app.branch("release", &bump, false).unwrap();
+        let checkout = Repository::open(dir.path().join("app/vendor/library")).unwrap();
+        commit_files(
+            &checkout,
+            &[("lib.rs", "one\ntwo\n")],
+            "Carol",
+            1749656964,
+            "more library code",
+        );
+        let mut index = app.index().unwrap();
+        index.add_path(Path::new("vendor/library")).unwrap();
+        index.write().unwrap();
+        commit_index(&app, "Dave", 1749756964, "bump library");
+
+        // --ref picks the bumps on that superproject branch
+        let target = Target::open(&file, false).unwrap();
+        let last_bump = |rev| {
+            let super_target = target.superproject_target(rev).unwrap().unwrap();
+            let commits = last_commits(&super_target, None).unwrap();
+            commits[0].author().name().unwrap().to_string()
+        };
+        assert_eq!(last_bump(None), "Dave");
+        assert_eq!(last_bump(Some("release")), "Bob");
+        assert!(target.superproject_target(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_deleted_file_reports_deletion_and_last_edit() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("keep.txt", "keep\n"), ("gone.txt", "one\n")],
+            "Alice",
+            1749456964,
+            "add files",
+        );
+        commit_files(
+            &repo,
+            &[("gone.txt", "one\ntwo\n")],
+            "Bob",
+            1749556964,
+            "edit gone",
+        );
+        remove_files(&repo, &["gone.txt"], "Carol", 1749656964, "remove gone");
+
+        let gone = workdir_target(&dir.path().join("gone.txt"));
+        let last = plain_last_commit(&gone).unwrap();
+        let lines: Vec<&str> = last.lines().collect();
+        assert_eq!(lines[0], "Deleted in:");
+        assert!(
+            lines[1].ends_with("Carol - 11 Jun 2025: remove gone"),
+            "{}",
+            last
+        );
+        assert_eq!(lines[2], "Last edited before deletion:");
+        assert!(
+            lines[3].ends_with("Bob - 10 Jun 2025: edit gone"),
+            "{}",
+            last
+        );
+
+        // Blame shows the file as it was before deletion
+        let blame = get_blame(
+            &gone,
+            OutputOptions {
+                no_color: true,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(blame.contains("│    2 │ two"), "{}", blame);
+
+        let never = workdir_target(&dir.path().join("never.txt"));
+        let error = plain_last_commit(&never).unwrap_err();
+        assert!(error.to_string().contains("never tracked"), "{}", error);
+    }
+
+    #[test]
+    fn test_deletion_before_merge_is_not_blamed_on_the_merge() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let start = commit_files(
+            &repo,
+            &[("gone.txt", "one\n")],
+            "Alice",
+            1749456964,
+            "add gone",
+        );
+        // A branch that still has the file...
+        let feature = commit_files(
+            &repo,
+            &[("feature.txt", "feature\n")],
+            "Bob",
+            1749556964,
+            "add feature",
+        );
+        repo.reset(
+            &repo.find_object(start, None).unwrap(),
+            git2::ResetType::Hard,
+            None,
+        )
+        .unwrap();
+        // ...is merged after the file was deleted on the main line
+        remove_files(&repo, &["gone.txt"], "Carol", 1749656964, "delete gone");
+        stage_files(&repo, &[("feature.txt", "feature\n")]);
+        commit_merge(&repo, feature, "Dave", 1749756964, "Merge feature");
+
+        let gone = workdir_target(&dir.path().join("gone.txt"));
+        let last = plain_last_commit(&gone).unwrap();
+        let lines: Vec<&str> = last.lines().collect();
+        assert_eq!(lines[0], "Deleted in:");
+        assert!(
+            lines[1].ends_with("Carol - 11 Jun 2025: delete gone"),
+            "{}",
+            last
+        );
+
+        let created = plain_origin(&gone, Origin::Created).unwrap();
+        assert!(
+            created.contains("Alice - 09 Jun 2025: add gone"),
+            "{}",
+            created
+        );
+    }
+
+    #[test]
+    fn test_created_follows_renames() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("README.md", "readme\n")],
+            "Alice",
+            1749456964,
+            "init",
+        );
+        commit_files(
+            &repo,
+            &[("src/old.rs", "fn a() {}\nfn b() {}\nfn c() {}\n")],
+            "Bob",
+            1749556964,
+            "add old module",
+        );
+        // Rename in a single commit
+        std::fs::remove_file(dir.path().join("src/old.rs")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("src/old.rs")).unwrap();
+        index.write().unwrap();
+        commit_files(
+            &repo,
+            &[("src/new.rs", "fn a() {}\nfn b() {}\nfn c() {}\n")],
+            "Carol",
+            1749656964,
+            "rename module",
+        );
+        commit_files(
+            &repo,
+            &[("src/new.rs", "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\n")],
+            "Dave",
+            1749756964,
+            "extend module",
+        );
+
+        let file = workdir_target(&dir.path().join("src/new.rs"));
+        let created = plain_origin(&file, Origin::Created).unwrap();
+        assert_eq!(
+            created,
+            format!(
+                "{} Bob - 10 Jun 2025: add old module\nOriginally created as 'src/old.rs'",
+                &created[..7]
+            )
+        );
+
+        let both = plain_origin(&file, Origin::Both).unwrap();
+        let lines: Vec<&str> = both.lines().collect();
+        assert_eq!(lines[0], "Created:");
+        assert!(
+            lines[1].ends_with("Bob - 10 Jun 2025: add old module"),
+            "{}",
+            both
+        );
+        assert_eq!(lines[3], "Last edited:");
+        assert!(
+            lines[4].ends_with("Dave - 12 Jun 2025: extend module"),
+            "{}",
+            both
+        );
+
+        // Directories are created by the first commit that adds anything below them
+        let src = workdir_target(&dir.path().join("src"));
+        let created = plain_origin(&src, Origin::Created).unwrap();
+        assert!(
+            created.ends_with("Bob - 10 Jun 2025: add old module"),
+            "{}",
+            created
+        );
+
+        let root = workdir_target(dir.path());
+        let created = plain_origin(&root, Origin::Created).unwrap();
+        assert!(
+            created.ends_with("Alice - 09 Jun 2025: init"),
+            "{}",
+            created
+        );
+    }
+
+    #[test]
+    fn test_all_branches_finds_newest_change_and_its_branch() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        let base = commit_files(
+            &repo,
+            &[("lib.rs", "one\n")],
+            "Alice",
+            1749456964,
+            "add lib",
+        );
+        let main_ref = repo.head().unwrap().name().unwrap().to_string();
+
+        // Commit on a release branch, then switch back
+        repo.branch("release/2.x", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        repo.set_head("refs/heads/release/2.x").unwrap();
+        commit_files(
+            &repo,
+            &[("lib.rs", "one\ntwo\n")],
+            "Bob",
+            1749556964,
+            "backport fix",
+        );
+        repo.set_head(&main_ref).unwrap();
+
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "lib.rs".to_string(),
+        };
+        let on_head = plain_last_commit(&target).unwrap();
+        assert!(on_head.contains("Alice"), "{}", on_head);
+
+        let any = get_last_commit_all_branches(&target, true, false, false, false).unwrap();
+        assert!(any.contains("Bob - 10 Jun 2025: backport fix"), "{}", any);
+        assert!(any.ends_with("On branch: release/2.x"), "{}", any);
+
+        // --ref reads the path at the branch instead of HEAD
+        let on_release = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: Some("release/2.x".to_string()),
+            path: "lib.rs".to_string(),
+        };
+        let last = plain_last_commit(&on_release).unwrap();
+        assert!(last.contains("Bob"), "{}", last);
+    }
+
+    #[test]
+    fn test_all_branches_finds_paths_missing_from_disk() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let base = commit_files(&repo, &[("a.rs", "a\n")], "Alice", 1749456964, "add a");
+        let main_ref = repo.head().unwrap().name().unwrap().to_string();
+
+        // A file that only exists on another branch, so not in the working copy
+        repo.branch("feature", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        repo.set_head("refs/heads/feature").unwrap();
+        commit_files(
+            &repo,
+            &[("src/feature.rs", "f\n")],
+            "Bob",
+            1749556964,
+            "add feature",
+        );
+        repo.set_head(&main_ref).unwrap();
+        std::fs::remove_dir_all(dir.path().join("src")).unwrap();
+
+        let on_disk = workdir_target(&dir.path().join("src/feature.rs"));
+        let any = get_last_commit_all_branches(&on_disk, true, false, false, false).unwrap();
+        assert!(any.contains("Bob - 10 Jun 2025: add feature"), "{}", any);
+        assert!(any.ends_with("On branch: feature"), "{}", any);
+
+        let in_tree = TargetPath::Tree {
+            git_dir: repo.path().to_path_buf(),
+            rev: None,
+            path: "src/feature.rs".to_string(),
+        };
+        let any = get_last_commit_all_branches(&in_tree, true, false, false, false).unwrap();
+        assert!(any.ends_with("On branch: feature"), "{}", any);
+    }
+
+    #[test]
+    fn test_working_tree_status() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[
+                ("src/a.rs", "a\n"),
+                ("src/b.rs", "b\n"),
+                (".gitignore", "*.log\n"),
+            ],
+            "Alice",
+            1749456964,
+            "init",
+        );
+        let last_edit = |path: &str| {
+            let target = workdir_target(&dir.path().join(path));
+            plain_last_commit(&target).unwrap()
+        };
+
+        assert_eq!(last_edit("src/a.rs").lines().count(), 1);
+        assert!(!last_edit("src").contains("Uncommitted"));
+
+        std::fs::write(dir.path().join("src/a.rs"), "changed\n").unwrap();
+        let output = last_edit("src/a.rs");
+        assert!(
+            output.starts_with("Modified in working tree (not committed yet)\n"),
+            "{}",
+            output
+        );
+        assert!(output.ends_with("Alice - 09 Jun 2025: init"), "{}", output);
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/a.rs")).unwrap();
+        index.write().unwrap();
+        assert!(last_edit("src/a.rs").starts_with("Staged changes"));
+
+        // Untracked and ignored files report their status instead of an error
+        std::fs::write(dir.path().join("src/new.rs"), "new\n").unwrap();
+        assert_eq!(last_edit("src/new.rs"), "Untracked (not committed yet)");
+        std::fs::write(dir.path().join("src/debug.log"), "log\n").unwrap();
+        assert!(last_edit("src/debug.log").starts_with("Ignored"));
+
+        let output = last_edit("src");
+        assert!(
+            output.starts_with("Uncommitted changes: 1 staged, 1 untracked\n"),
+            "{}",
+            output
+        );
+
+        // A directory with nothing committed yet
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/guide.md"), "guide\n").unwrap();
+        assert_eq!(last_edit("docs"), "Uncommitted changes: 1 untracked");
+    }
+
+    #[test]
+    fn test_normalize_tree_path() {
+        assert_eq!(normalize_tree_path("./src/"), PathBuf::from("src"));
+        assert_eq!(normalize_tree_path("."), PathBuf::from(""));
+        assert_eq!(
+            normalize_tree_path("src/main.rs"),
+            PathBuf::from("src/main.rs")
+        );
+    }
+
+    #[test]
+    fn test_bare_repository_last_commit_and_blame() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("src/main.rs", "one\n")],
+            "Alice",
+            1749456964,
+            "add main",
+        );
+        commit_files(
+            &repo,
+            &[("src/main.rs", "one\ntwo\n")],
+            "Bob",
+            1749556964,
+            "extend main",
+        );
+
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "src/main.rs".to_string(),
+        };
+        let last = plain_last_commit(&target).unwrap();
+        assert!(last.contains("Bob - 10 Jun 2025: extend main"), "{}", last);
+
+        let blame = get_blame(
+            &target,
+            OutputOptions {
+                no_color: true,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(blame.contains("Alice"), "{}", blame);
+        assert!(blame.contains("│    2 │ two"), "{}", blame);
+
+        // At the first revision only Alice's line exists
+        let first = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: Some("HEAD~1".to_string()),
+            path: "src/main.rs".to_string(),
+        };
+        let blame = get_blame(
+            &first,
+            OutputOptions {
+                no_color: true,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!blame.contains("Bob"), "{}", blame);
+
+        let root = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: ".".to_string(),
+        };
+        assert!(
+            get_blame(
+                &root,
+                OutputOptions {
+                    no_color: true,
+                    ..Default::default()
+                },
+                None,
+                None
+            )
+            .is_err()
+        );
+        assert!(plain_last_commit(&root).is_ok());
+    }
+
+    #[test]
+    fn test_links_from_origin_remote() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit_files(&repo, &[("a.txt", "one\n")], "Alice", 1749456964, "add a");
+        let file = workdir_target(&dir.path().join("a.txt"));
+
+        // Without a known remote there is nothing to link to
+        let plain = get_last_commit(
+            &file,
+            OutputOptions {
+                no_color: true,
+                links: true,
+                ..Default::default()
+            },
+            None,
+            false,
+            Origin::LastEdit,
+        )
+        .unwrap();
+        assert!(!plain.contains("https://"), "{}", plain);
+
+        repo.remote("origin", "git@github.com:owner/repo.git")
+            .unwrap();
+        let linked = get_last_commit(
+            &file,
+            OutputOptions {
+                no_color: true,
+                links: true,
+                ..Default::default()
+            },
+            None,
+            false,
+            Origin::LastEdit,
+        )
+        .unwrap();
+        assert!(
+            linked.ends_with(&format!(
+                "\n   https://github.com/owner/repo/commit/{}",
+                first
+            )),
+            "{}",
+            linked
+        );
+
+        let blame = get_blame(
+            &file,
+            OutputOptions {
+                no_color: true,
+                links: true,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(
+            blame.contains(&format!(
+                "└─ https://github.com/owner/repo/blob/{}/a.txt#L1",
+                first
+            )),
+            "{}",
+            blame
+        );
+    }
+
+    #[test]
+    fn test_refs_shown_in_text() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("a.txt", "one\n")],
+            "Alice",
+            1749456964,
+            "Fix login (#42)\n\nFor PLAT-7",
+        );
+        let file = workdir_target(&dir.path().join("a.txt"));
+
+        let last = plain_last_commit(&file).unwrap();
+        assert!(
+            last.ends_with("Alice - 09 Jun 2025 [#42 PLAT-7]: Fix login (#42)"),
+            "{}",
+            last
+        );
+
+        // In blame they follow the commit message
+        let blame = get_blame(
+            &file,
+            OutputOptions {
+                no_color: true,
+                commit_message: true,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(blame.contains("└─ Fix login (#42)\n"), "{}", blame);
+        assert!(blame.contains("└─ #42 PLAT-7\n"), "{}", blame);
+    }
+
+    #[test]
+    fn test_blame_detects_moved_lines() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = "fn first_function() {\n    do_first_thing();\n}\n";
+        let second = "fn second_function() {\n    do_second_thing();\n}\n";
+        commit_files(
+            &repo,
+            &[("a.rs", &format!("{}\n{}", first, second))],
+            "Alice",
+            1749456964,
+            "add functions",
+        );
+        commit_files(
+            &repo,
+            &[("a.rs", &format!("{}\n{}", second, first))],
+            "Bob",
+            1749556964,
+            "swap functions",
+        );
+        let file = workdir_target(&dir.path().join("a.rs"));
+
+        let blame = |copies| {
+            get_blame(
+                &file,
+                OutputOptions {
+                    no_color: true,
+                    copies,
+                    ..Default::default()
+                },
+                None,
+                None,
+            )
+        };
+        let plain = blame(CopyDetection::default()).unwrap();
+        assert!(plain.contains("│ Bob             │ 10 Jun │    5 │ fn first_function() {\n"));
+
+        // The moved function keeps its author, with where it came from below
+        let moves = blame(CopyDetection {
+            moves: true,
+            copies: 0,
+        })
+        .unwrap();
+        assert!(
+            moves.contains("│ Alice           │ 09 Jun │    5 │ fn first_function() {\n"),
+            "{}",
+            moves
+        );
+        assert!(moves.contains("│ └─ from a.rs:1\n"), "{}", moves);
+        // The blank line between them is too short to count as moved
+        assert!(
+            moves.contains("│ Bob             │ 10 Jun │    4 │ \n"),
+            "{}",
+            moves
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use anyhow::{Result, anyhow};
+use git2::{Delta, DiffFindOptions, DiffOptions, Patch};
+use std::path::PathBuf;
+
+use crate::git::{ColorScheme, CommitDecorations, CommitInfo, Target, TargetPath};
+use crate::utils::shown_lines;
+
+/// Line numbers of a diff hunk, as in its "@@ -old_start,old_lines +new_start,new_lines @@" header
+#[derive(Clone, Copy, Debug)]
+struct HunkRange {
+    old_start: usize,
+    old_lines: usize,
+    new_start: usize,
+    new_lines: usize,
+}
+
+impl HunkRange {
+    /// Whether the hunk changes any line in the (new side) range start..=end
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        if self.new_lines == 0 {
+            // A pure deletion sits between new_start and new_start + 1
+            start <= self.new_start && self.new_start < end
+        } else {
+            self.new_start <= end && self.new_start + self.new_lines > start
+        }
+    }
+}
+
+/// Map a line on the new side of a diff to the old side
+/// Lines inside a changed hunk map to the start (or end) of the hunk's old lines
+fn map_line(hunks: &[HunkRange], line: usize, is_end: bool) -> usize {
+    let mut delta: isize = 0;
+
+    for hunk in hunks {
+        let first_new = if hunk.new_lines == 0 {
+            hunk.new_start + 1
+        } else {
+            hunk.new_start
+        };
+        if line < first_new {
+            break;
+        }
+
+        if hunk.new_lines > 0 && line < hunk.new_start + hunk.new_lines {
+            let first_old = if hunk.old_lines == 0 {
+                hunk.old_start + 1
+            } else {
+                hunk.old_start
+            };
+            return if is_end {
+                first_old + hunk.old_lines - 1
+            } else {
+                first_old
+            };
+        }
+
+        delta += hunk.new_lines as isize - hunk.old_lines as isize;
+    }
+
+    (line as isize - delta) as usize
+}
+
+/// Map a line range through a commit's hunks to the parent's version of the file
+/// Returns None when every line in the range was introduced by the commit
+fn map_range(hunks: &[HunkRange], start: usize, end: usize) -> Option<(usize, usize)> {
+    let old_start = map_line(hunks, start, false);
+    let old_end = map_line(hunks, end, true);
+
+    (old_start <= old_end).then_some((old_start, old_end))
+}
+
+/// The line numbers of every hunk in a patch
+fn patch_hunks(patch: &Patch) -> Result<Vec<HunkRange>> {
+    let mut hunks = Vec::new();
+    for hunk_index in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(hunk_index)?;
+        hunks.push(HunkRange {
+            old_start: hunk.old_start() as usize,
+            old_lines: hunk.old_lines() as usize,
+            new_start: hunk.new_start() as usize,
+            new_lines: hunk.new_lines() as usize,
+        });
+    }
+    Ok(hunks)
+}
+
+/// Show every commit that modified a line range, newest first, with the hunks that changed it
+/// The range is tracked back through edits and renames, similar to "git log -L"
+pub fn get_line_history(
+    path: &TargetPath,
+    no_color: bool,
+    commit_message: bool,
+    links: bool,
+    line_range: (usize, usize),
+) -> Result<String> {
+    let target = Target::open(path, true)?;
+    let repo = &target.repo;
+    let colors = ColorScheme::new(no_color);
+    let decorations = CommitDecorations::from_repo(repo)?;
+
+    // -L counts the lines as they are on disk, while history starts from the committed file
+    let committed = target.committed_content()?;
+    let (mut start, mut end) = if target.full_path.is_some() && target.start.is_none() {
+        let content = target.read_content()?;
+        let line_count = content.lines().count();
+        let (start, end) = shown_lines(path, Some(line_range), line_count)?;
+        let mut options = DiffOptions::new();
+        options.context_lines(0);
+        let patch = Patch::from_buffers(
+            committed.as_bytes(),
+            None,
+            content.as_bytes(),
+            None,
+            Some(&mut options),
+        )?;
+        match map_range(&patch_hunks(&patch)?, start, end) {
+            Some(range) => range,
+            None => {
+                return Err(anyhow!(
+                    "Lines {}-{} of {} are not committed yet, so they have no history",
+                    start,
+                    end,
+                    path
+                ));
+            }
+        }
+    } else {
+        shown_lines(path, Some(line_range), committed.lines().count())?
+    };
+
+    let mut current_path: PathBuf = target.relative_path.clone();
+    let mut commit = target.start_commit()?;
+    let mut entries = Vec::new();
+
+    loop {
+        let tree = commit.tree()?;
+        let parent = commit.parents().next();
+        let parent_tree = parent.as_ref().map(|parent| parent.tree()).transpose()?;
+
+        let new_id = tree.get_path(&current_path)?.id();
+        let old_id = parent_tree
+            .as_ref()
+            .and_then(|parent_tree| parent_tree.get_path(&current_path).ok())
+            .map(|entry| entry.id());
+
+        // Unchanged in this commit, keep walking back
+        if old_id == Some(new_id) {
+            match parent {
+                Some(parent) => {
+                    commit = parent;
+                    continue;
+                }
+                None => break,
+            }
+        }
+
+        let mut diff_options = DiffOptions::new();
+        diff_options.context_lines(0);
+        if old_id.is_some() {
+            diff_options.pathspec(&current_path);
+        }
+        let mut diff =
+            repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut diff_options))?;
+
+        // The file is new at this path, so look for the path it was renamed from
+        if old_id.is_none() && parent_tree.is_some() {
+            diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+        }
+
+        let delta_index = diff
+            .deltas()
+            .position(|delta| delta.new_file().path() == Some(current_path.as_path()));
+        let Some(delta_index) = delta_index else {
+            break;
+        };
+        let delta = diff.get_delta(delta_index).expect("delta index is valid");
+        let status = delta.status();
+        let old_path = delta.old_file().path().map(|path| path.to_path_buf());
+
+        let Some(patch) = Patch::from_diff(&diff, delta_index)? else {
+            break;
+        };
+
+        let hunks = patch_hunks(&patch)?;
+        let old_range = map_range(&hunks, start, end);
+
+        // Show only the hunk lines that fall inside the tracked range
+        let mut hunk_output = String::new();
+        for (hunk_index, range) in hunks.iter().enumerate() {
+            if !range.overlaps(start, end) {
+                continue;
+            }
+
+            let (hunk, line_total) = patch.hunk(hunk_index)?;
+
+            hunk_output.push_str(&format!(
+                "{}{}{}",
+                colors.date,
+                String::from_utf8_lossy(hunk.header()).trim_end(),
+                colors.reset
+            ));
+            hunk_output.push('\n');
+            for line_index in 0..line_total {
+                let line = patch.line_in_hunk(hunk_index, line_index)?;
+                let in_range = match (line.new_lineno(), line.old_lineno()) {
+                    (Some(new_line), _) => (start..=end).contains(&(new_line as usize)),
+                    (None, Some(old_line)) => old_range.is_some_and(|(old_start, old_end)| {
+                        (old_start..=old_end).contains(&(old_line as usize))
+                    }),
+                    (None, None) => false,
+                };
+                if !in_range {
+                    continue;
+                }
+
+                let color = match line.origin() {
+                    '+' => colors.added,
+                    '-' => colors.removed,
+                    _ => "",
+                };
+                hunk_output.push_str(&format!(
+                    "{}{}{}{}",
+                    color,
+                    line.origin(),
+                    String::from_utf8_lossy(line.content()).trim_end_matches(['\r', '\n']),
+                    colors.reset
+                ));
+                hunk_output.push('\n');
+            }
+        }
+
+        if !hunk_output.is_empty() {
+            let commit_info = CommitInfo::describe(&commit, &decorations);
+            entries.push(format!(
+                "{}\n{}",
+                commit_info.format_regular(&colors, commit_message, links),
+                hunk_output.trim_end()
+            ));
+        }
+
+        // The whole file (and so the range) was introduced by this commit
+        if status == Delta::Added {
+            break;
+        }
+        if status == Delta::Renamed
+            && let Some(old_path) = old_path
+        {
+            current_path = old_path;
+        }
+
+        match old_range {
+            Some((old_start, old_end)) => (start, end) = (old_start, old_end),
+            None => break,
+        }
+
+        match parent {
+            Some(parent) => commit = parent,
+            None => break,
+        }
+    }
+
+    if entries.is_empty() {
+        return Err(anyhow!(
+            "No commits found for lines {}-{} of {}",
+            line_range.0,
+            line_range.1,
+            path
+        ));
+    }
+
+    Ok(entries.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use git2::Repository;
+    use tempfile::tempdir;
+
+    fn hunk(old_start: usize, old_lines: usize, new_start: usize, new_lines: usize) -> HunkRange {
+        HunkRange {
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+        }
+    }
+
+    #[test]
+    fn test_map_range_shifts_past_earlier_hunks() {
+        // Two lines inserted after line 2 shift later lines down by two
+        let hunks = [hunk(2, 0, 3, 2)];
+        assert_eq!(map_range(&hunks, 10, 12), Some((8, 10)));
+        // Lines before the hunk are unchanged
+        assert_eq!(map_range(&hunks, 1, 2), Some((1, 2)));
+    }
+
+    #[test]
+    fn test_map_range_through_modified_lines() {
+        // Lines 5-6 replaced by three new lines 5-7
+        let hunks = [hunk(5, 2, 5, 3)];
+        assert_eq!(map_range(&hunks, 6, 9), Some((5, 8)));
+    }
+
+    #[test]
+    fn test_map_range_fully_added() {
+        let hunks = [hunk(4, 0, 5, 3)];
+        assert_eq!(map_range(&hunks, 5, 7), None);
+    }
+
+    #[test]
+    fn test_map_range_after_deletion() {
+        // Lines 3-4 deleted, so new line 3 was old line 5
+        let hunks = [hunk(3, 2, 2, 0)];
+        assert_eq!(map_range(&hunks, 3, 3), Some((5, 5)));
+        assert!(hunks[0].overlaps(1, 3));
+        assert!(!hunks[0].overlaps(3, 4));
+    }
+
+    #[test]
+    fn test_line_history_follows_edits_and_renames() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("old.rs", "a\nb\nc\n")],
+            "Alice",
+            1749456964,
+            "add file",
+        );
+        commit_files(
+            &repo,
+            &[("old.rs", "a\nB\nc\n")],
+            "Bob",
+            1749556964,
+            "change b",
+        );
+        // Rename the file with an unrelated edit at the top
+        let mut index = repo.index().unwrap();
+        index.remove_path(std::path::Path::new("old.rs")).unwrap();
+        index.write_tree().unwrap();
+        commit_files(
+            &repo,
+            &[("new.rs", "header\na\nB\nc\n")],
+            "Carol",
+            1749656964,
+            "rename",
+        );
+
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "new.rs".to_string(),
+        };
+        let history = get_line_history(&target, true, false, false, (3, 3)).unwrap();
+
+        let authors: Vec<&str> = history
+            .lines()
+            .filter(|line| line.contains(" - "))
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(authors, vec!["Bob", "Alice"], "{}", history);
+        assert!(history.contains("-b\n+B"), "{}", history);
+        assert!(!history.contains("header"), "{}", history);
+    }
+
+    #[test]
+    fn test_line_history_maps_dirty_working_copy_lines() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("file.rs", "a\nb\nc\n")],
+            "Alice",
+            1749456964,
+            "add file",
+        );
+        commit_files(
+            &repo,
+            &[("file.rs", "a\nB\nc\n")],
+            "Bob",
+            1749556964,
+            "change b",
+        );
+        // Two uncommitted lines at the top shift "B" down to line 4 on disk
+        std::fs::write(dir.path().join("file.rs"), "x\ny\na\nB\nc\n").unwrap();
+
+        let target = TargetPath::Workdir(dir.path().join("file.rs").to_string_lossy().to_string());
+        let history = get_line_history(&target, true, false, false, (4, 4)).unwrap();
+        assert!(history.contains("-b\n+B"), "{}", history);
+        assert!(!history.contains("+a"), "{}", history);
+
+        let error = get_line_history(&target, true, false, false, (1, 2)).unwrap_err();
+        assert!(error.to_string().contains("not committed yet"), "{}", error);
+    }
+}
@@ -0,0 +1,293 @@
+use anyhow::{Result, anyhow};
+use git2::{DiffOptions, ObjectType, Patch};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::cli::{Format, HotspotSort};
+use crate::git::{ColorScheme, Target, TargetPath, first_parent_diff};
+use crate::utils::format_timestamp_day_month_year;
+
+/// How often, and by how many people, a file was changed
+#[derive(Serialize)]
+struct Hotspot {
+    path: String,
+    commits: usize,
+    authors: usize,
+    lines_added: usize,
+    lines_removed: usize,
+    /// Current size of the file in lines
+    lines: usize,
+    /// The value files are ranked by
+    score: usize,
+}
+
+#[derive(Default)]
+struct Churn {
+    commits: usize,
+    authors: HashSet<String>,
+    lines_added: usize,
+    lines_removed: usize,
+}
+
+/// Rank the files under `path` that changed the most, newest history first
+/// Only non-merge commits since `since` count, and only files that still exist
+pub fn get_hotspots(
+    path: &TargetPath,
+    since: Option<i64>,
+    sort: HotspotSort,
+    weight_by_size: bool,
+    max: usize,
+    format: Format,
+    no_color: bool,
+) -> Result<String> {
+    if !matches!(format, Format::Text | Format::Json) {
+        return Err(anyhow!("wer hotspots only supports --format text and json"));
+    }
+
+    let hotspots = find_hotspots(path, since, sort, weight_by_size)?;
+    let hotspots = &hotspots[..hotspots.len().min(max)];
+
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(hotspots)?),
+        _ => Ok(format_table(
+            path,
+            hotspots,
+            since,
+            weight_by_size,
+            no_color,
+        )),
+    }
+}
+
+fn find_hotspots(
+    path: &TargetPath,
+    since: Option<i64>,
+    sort: HotspotSort,
+    weight_by_size: bool,
+) -> Result<Vec<Hotspot>> {
+    let target = Target::open(path, false)?;
+    let repo = &target.repo;
+
+    let mut revwalk = target.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+
+    let mut churn: HashMap<String, Churn> = HashMap::new();
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        if since.is_some_and(|since| commit.time().seconds() < since) {
+            break;
+        }
+        // Merges repeat the changes of the commits they bring in
+        if commit.parent_count() > 1 {
+            continue;
+        }
+
+        let mut diff_options = DiffOptions::new();
+        if !target.relative_path.as_os_str().is_empty() {
+            diff_options.pathspec(&target.relative_path);
+        }
+        let diff = first_parent_diff(repo, &commit, Some(&mut diff_options))?;
+        let author = commit.author().email().unwrap_or("").to_lowercase();
+
+        for delta_index in 0..diff.deltas().len() {
+            let Some(file) = diff
+                .get_delta(delta_index)
+                .and_then(|delta| delta.new_file().path().or(delta.old_file().path()))
+            else {
+                continue;
+            };
+            let file = file.to_string_lossy().to_string();
+
+            let (added, removed) = match Patch::from_diff(&diff, delta_index)? {
+                Some(patch) => {
+                    let (_, added, removed) = patch.line_stats()?;
+                    (added, removed)
+                }
+                None => (0, 0),
+            };
+
+            let entry = churn.entry(file).or_default();
+            entry.commits += 1;
+            entry.authors.insert(author.clone());
+            entry.lines_added += added;
+            entry.lines_removed += removed;
+        }
+    }
+
+    // Rank files as they are now, skipping ones that were deleted or renamed away
+    let tree = target.start_commit()?.tree()?;
+    let mut hotspots = Vec::new();
+    for (file, churn) in churn {
+        let Ok(entry) = tree.get_path(std::path::Path::new(&file)) else {
+            continue;
+        };
+        if entry.kind() != Some(ObjectType::Blob) {
+            continue;
+        }
+        let blob = repo.find_blob(entry.id())?;
+        let lines = if blob.is_binary() {
+            0
+        } else {
+            blob.content().iter().filter(|&&byte| byte == b'\n').count()
+        };
+
+        let key = match sort {
+            HotspotSort::Commits => churn.commits,
+            HotspotSort::Authors => churn.authors.len(),
+            HotspotSort::Churn => churn.lines_added + churn.lines_removed,
+        };
+        hotspots.push(Hotspot {
+            path: file,
+            commits: churn.commits,
+            authors: churn.authors.len(),
+            lines_added: churn.lines_added,
+            lines_removed: churn.lines_removed,
+            lines,
+            score: if weight_by_size { key * lines } else { key },
+        });
+    }
+
+    hotspots.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.commits.cmp(&a.commits))
+            .then(a.path.cmp(&b.path))
+    });
+
+    Ok(hotspots)
+}
+
+fn format_table(
+    path: &TargetPath,
+    hotspots: &[Hotspot],
+    since: Option<i64>,
+    weight_by_size: bool,
+    no_color: bool,
+) -> String {
+    let colors = ColorScheme::new(no_color);
+    let mut result = format!("Hotspots in {}", path);
+    if let Some(since) = since {
+        result.push_str(&format!(
+            " since {}{}{}",
+            colors.date,
+            format_timestamp_day_month_year(since),
+            colors.reset
+        ));
+    }
+    result.push('\n');
+
+    if hotspots.is_empty() {
+        result.push_str("\nNo changes found");
+        return result;
+    }
+
+    result.push_str(&format!(
+        "\n{:>7}  {:>7}  {:>8}  {:>8}  {:>6}",
+        "Commits", "Authors", "Added", "Removed", "Lines"
+    ));
+    if weight_by_size {
+        result.push_str(&format!("  {:>9}", "Score"));
+    }
+    result.push_str("  Path");
+
+    for hotspot in hotspots {
+        result.push_str(&format!(
+            "\n{:>7}  {:>7}  {}{:>8}{}  {}{:>8}{}  {:>6}",
+            hotspot.commits,
+            hotspot.authors,
+            colors.added,
+            format!("+{}", hotspot.lines_added),
+            colors.reset,
+            colors.removed,
+            format!("-{}", hotspot.lines_removed),
+            colors.reset,
+            hotspot.lines
+        ));
+        if weight_by_size {
+            result.push_str(&format!("  {:>9}", hotspot.score));
+        }
+        result.push_str(&format!(
+            "  {}{}{}",
+            colors.commit, hotspot.path, colors.reset
+        ));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{commit_files, remove_files};
+    use git2::Repository;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hotspots_ranking() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[
+                ("src/busy.rs", "1\n"),
+                ("src/big.rs", "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n"),
+                ("src/gone.rs", "1\n"),
+                ("README.md", "readme\n"),
+            ],
+            "Alice",
+            1749456964,
+            "init",
+        );
+        commit_files(
+            &repo,
+            &[("src/busy.rs", "1\n2\n")],
+            "Bob",
+            1749556964,
+            "more",
+        );
+        commit_files(
+            &repo,
+            &[("src/busy.rs", "2\n")],
+            "Carol",
+            1749656964,
+            "less",
+        );
+        commit_files(
+            &repo,
+            &[("src/gone.rs", "1\n2\n")],
+            "Bob",
+            1749656964,
+            "edit",
+        );
+        remove_files(&repo, &["src/gone.rs"], "Bob", 1749756964, "remove");
+
+        let src = TargetPath::Workdir(dir.path().join("src").to_string_lossy().to_string());
+        let hotspots = find_hotspots(&src, None, HotspotSort::Commits, false).unwrap();
+        let ranked: Vec<(&str, usize, usize, usize, usize)> = hotspots
+            .iter()
+            .map(|h| {
+                (
+                    h.path.as_str(),
+                    h.commits,
+                    h.authors,
+                    h.lines_added,
+                    h.lines_removed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![("src/busy.rs", 3, 3, 2, 1), ("src/big.rs", 1, 1, 10, 0)]
+        );
+
+        // Weighted by size, the big file outranks the busy one
+        let weighted = find_hotspots(&src, None, HotspotSort::Commits, true).unwrap();
+        assert_eq!(weighted[0].path, "src/big.rs");
+        assert_eq!(weighted[0].score, 10);
+
+        // Only commits after --since count
+        let recent = find_hotspots(&src, Some(1749500000), HotspotSort::Authors, false).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!((recent[0].commits, recent[0].authors), (2, 2));
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{Result, anyhow};
+use git2::Oid;
+use std::collections::HashMap;
+
+use crate::copies::CopyDetection;
+use crate::git::{Target, TargetPath, blame_at};
+use crate::syntax::SyntaxHighlighter;
+use crate::utils::{format_timestamp_day_month_year, shown_lines};
+
+/// Commit details shown in the gutter and its tooltip
+struct CommitDetails {
+    hash: String,
+    author: String,
+    email: String,
+    time: i64,
+    message: String,
+}
+
+/// Render blame for a file as a standalone HTML page, with inline styles and no external assets
+/// Lines are grouped into hunks by commit, shaded by age, and the commit cell's tooltip holds
+/// the full commit message
+pub fn get_blame_html(path: &TargetPath, line_range: Option<(usize, usize)>) -> Result<String> {
+    let target = Target::open(path, true)?;
+    let repo = &target.repo;
+
+    let blame = blame_at(
+        repo,
+        &target.relative_path,
+        target.start,
+        CopyDetection::default(),
+    )
+    .map_err(|e| anyhow!("Failed to get blame for file '{}': {}", path, e))?;
+    let content = target.read_content()?;
+
+    let highlighter = SyntaxHighlighter::new();
+    let lines = highlighter.highlight_html(&content, target.syntax_path())?;
+    let (start, end) = shown_lines(path, line_range, lines.len())?;
+
+    // The commit of every shown line, and the details of each commit
+    let mut line_commits = Vec::new();
+    let mut commits: HashMap<Oid, CommitDetails> = HashMap::new();
+    for line_num in start..=end {
+        let commit_id = blame.get_line(line_num).map(|hunk| hunk.final_commit_id());
+        if let Some(commit_id) = commit_id
+            && !commits.contains_key(&commit_id)
+        {
+            let commit = repo.find_commit(commit_id)?;
+            let author = commit.author();
+            commits.insert(
+                commit_id,
+                CommitDetails {
+                    hash: commit_id.to_string(),
+                    author: author.name().unwrap_or("Unknown").to_string(),
+                    email: author.email().unwrap_or("").to_string(),
+                    time: commit.time().seconds(),
+                    message: commit.message().unwrap_or("").trim_end().to_string(),
+                },
+            );
+        }
+        line_commits.push(commit_id);
+    }
+
+    let oldest = commits
+        .values()
+        .map(|commit| commit.time)
+        .min()
+        .unwrap_or(0);
+    let newest = commits
+        .values()
+        .map(|commit| commit.time)
+        .max()
+        .unwrap_or(0);
+    let (background, foreground) = highlighter.theme_colors();
+
+    let mut rows = String::new();
+    let mut previous: Option<Option<Oid>> = None;
+    for (index, commit_id) in line_commits.iter().enumerate() {
+        let line_num = start + index;
+        let details = commit_id.and_then(|commit_id| commits.get(&commit_id));
+
+        // A new hunk starts whenever the commit changes
+        if previous != Some(*commit_id) {
+            if previous.is_some() {
+                rows.push_str("</tbody>\n");
+            }
+            rows.push_str("<tbody class=\"hunk\">\n");
+        }
+        let first_in_hunk = previous != Some(*commit_id);
+        previous = Some(*commit_id);
+
+        let shade = details.map_or(0.0, |details| age_shade(details.time, oldest, newest));
+        let meta = match (details, first_in_hunk) {
+            (Some(details), true) => format!(
+                "<td class=\"commit\" title=\"{}\">{}</td><td class=\"author\">{}</td><td class=\"date\">{}</td>",
+                escape_html(&format!(
+                    "{}\n{} <{}>\n{}\n\n{}",
+                    details.hash,
+                    details.author,
+                    details.email,
+                    format_timestamp_day_month_year(details.time),
+                    details.message
+                )),
+                &details.hash[..7],
+                escape_html(&details.author),
+                format_timestamp_day_month_year(details.time)
+            ),
+            (None, true) => {
+                "<td class=\"commit\">~~~~~~~</td><td class=\"author\">Unknown</td><td class=\"date\"></td>"
+                    .to_string()
+            }
+            (_, false) => "<td class=\"commit\"></td><td class=\"author\"></td><td class=\"date\"></td>"
+                .to_string(),
+        };
+
+        rows.push_str(&format!(
+            "<tr style=\"--age: {:.2}\">{}<td class=\"line\">{}</td><td class=\"code\">{}</td></tr>\n",
+            shade,
+            meta,
+            line_num,
+            lines[line_num - 1]
+        ));
+    }
+    if previous.is_some() {
+        rows.push_str("</tbody>\n");
+    }
+
+    let title = escape_html(&format!("Blame: {}", path));
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ margin: 0; font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; background: {background}; color: {foreground}; }}
+h1 {{ font-size: 1rem; font-weight: 600; margin: 0; padding: 12px 16px; border-bottom: 1px solid rgba(255, 255, 255, 0.15); }}
+table {{ border-collapse: collapse; width: 100%; font-family: ui-monospace, Menlo, Consolas, monospace; font-size: 13px; }}
+tbody.hunk {{ border-top: 1px solid rgba(255, 255, 255, 0.15); }}
+td {{ padding: 0 8px; vertical-align: top; white-space: pre; }}
+td.commit, td.author, td.date {{ background: rgba(255, 166, 64, calc(0.05 + 0.45 * var(--age))); }}
+td.commit {{ cursor: help; text-decoration: underline dotted; }}
+td.author {{ max-width: 160px; overflow: hidden; text-overflow: ellipsis; }}
+td.line {{ text-align: right; opacity: 0.5; user-select: none; }}
+td.code {{ width: 100%; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+{rows}</table>
+</body>
+</html>
+"#
+    ))
+}
+
+/// 1 for the newest commit shown, 0 for the oldest
+fn age_shade(time: i64, oldest: i64, newest: i64) -> f64 {
+    if newest == oldest {
+        return 1.0;
+    }
+    (time - oldest) as f64 / (newest - oldest) as f64
+}
+
+/// Escape text for use in HTML content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use git2::Repository;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("a < b && \"c\" > 'd'"),
+            "a &lt; b &amp;&amp; &quot;c&quot; &gt; &#39;d&#39;"
+        );
+    }
+
+    #[test]
+    fn test_blame_html_page() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("main.rs", "fn main() {\n    let a = 1 < 2;\n}\n")],
+            "Alice",
+            1749456964,
+            "add main\n\nWith a longer explanation",
+        );
+        commit_files(
+            &repo,
+            &[(
+                "main.rs",
+                "fn main() {\n    let a = 1 < 2;\n    println!(\"{}\", a);\n}\n",
+            )],
+            "Bob",
+            1749556964,
+            "print it",
+        );
+
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "main.rs".to_string(),
+        };
+        let html = get_blame_html(&target, None).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        // Alice's two lines, Bob's line, Alice's closing brace
+        assert_eq!(html.matches("<tbody class=\"hunk\">").count(), 3);
+        assert!(
+            html.contains("add main\n\nWith a longer explanation\""),
+            "{}",
+            html
+        );
+        assert!(html.contains("Bob &lt;bob@example.com&gt;"));
+        // Code is escaped, and the newest commit gets the strongest shade
+        assert!(html.contains("&lt;"));
+        assert!(html.contains("<tr style=\"--age: 1.00\"><td class=\"commit\""));
+        // Nothing is loaded from elsewhere
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("<link"));
+        assert!(!html.contains("http"));
+
+        let range = get_blame_html(&target, Some((3, 3))).unwrap();
+        assert_eq!(range.matches("<tr ").count(), 1);
+
+        // An empty file is an empty page, not an error
+        commit_files(&repo, &[("empty", "")], "Alice", 1749656964, "add empty");
+        let empty = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "empty".to_string(),
+        };
+        let html = get_blame_html(&empty, None).unwrap();
+        assert_eq!(html.matches("<tr ").count(), 0);
+    }
+}
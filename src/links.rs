@@ -0,0 +1,281 @@
+use git2::{Oid, Repository};
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::utils::percent_encode_path;
+
+/// Web URLs for commits and files on the service hosting a repository's "origin" remote
+///
+/// GitHub, GitLab, Bitbucket and Gitea are recognised by host name. Self-hosted instances
+/// can set the URL layout with `wer.commitUrlTemplate` and `wer.fileUrlTemplate` in git
+/// config, using the placeholders {base} (e.g. "https://host/owner/repo"), {host}, {repo},
+/// {commit}, {path} and {line}.
+pub struct RemoteLinks {
+    base: String,
+    host: String,
+    repo: String,
+    commit_template: Option<String>,
+    file_template: Option<String>,
+}
+
+impl RemoteLinks {
+    /// Links for a repository, None when it has no origin or its host isn't known
+    pub fn from_repo(repo: &Repository) -> Option<Self> {
+        let remote = repo.find_remote("origin").ok()?;
+        let config = repo.config().ok()?;
+        let template = |name: &str| config.get_string(name).ok().filter(|t| !t.is_empty());
+        Self::new(
+            remote.url()?,
+            template("wer.commitUrlTemplate"),
+            template("wer.fileUrlTemplate"),
+        )
+    }
+
+    fn new(
+        url: &str,
+        commit_template: Option<String>,
+        file_template: Option<String>,
+    ) -> Option<Self> {
+        let (scheme, host, repo) = parse_remote_url(url)?;
+        let (default_commit, default_file) = match host_layout(&host) {
+            Some((commit, file)) => (Some(commit.to_string()), Some(file.to_string())),
+            None => (None, None),
+        };
+        let commit_template = commit_template.or(default_commit);
+        let file_template = file_template.or(default_file);
+        if commit_template.is_none() && file_template.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            base: format!("{}://{}/{}", scheme, host, repo),
+            host,
+            repo,
+            commit_template,
+            file_template,
+        })
+    }
+
+    /// Page of a commit
+    pub fn commit_url(&self, commit: Oid) -> Option<String> {
+        let template = self.commit_template.as_deref()?;
+        Some(self.fill(template, commit, Path::new(""), 0))
+    }
+
+    /// A line of a file as it was at a commit
+    pub fn file_url(&self, commit: Oid, path: &Path, line: usize) -> Option<String> {
+        let template = self.file_template.as_deref()?;
+        Some(self.fill(template, commit, path, line))
+    }
+
+    fn fill(&self, template: &str, commit: Oid, path: &Path, line: usize) -> String {
+        let path = path.to_string_lossy().replace('\\', "/");
+        template
+            .replace("{base}", &self.base)
+            .replace("{host}", &self.host)
+            .replace("{repo}", &self.repo)
+            .replace("{commit}", &commit.to_string())
+            .replace("{path}", &percent_encode_path(&path))
+            .replace("{line}", &line.to_string())
+    }
+}
+
+/// Commit and file URL templates of the hosting services recognised by host name
+fn host_layout(host: &str) -> Option<(&'static str, &'static str)> {
+    if host.contains("github") {
+        Some((
+            "{base}/commit/{commit}",
+            "{base}/blob/{commit}/{path}#L{line}",
+        ))
+    } else if host.contains("gitlab") {
+        Some((
+            "{base}/-/commit/{commit}",
+            "{base}/-/blob/{commit}/{path}#L{line}",
+        ))
+    } else if host.contains("bitbucket") {
+        Some((
+            "{base}/commits/{commit}",
+            "{base}/src/{commit}/{path}#lines-{line}",
+        ))
+    } else if host.contains("gitea") || host.contains("codeberg") || host.contains("forgejo") {
+        Some((
+            "{base}/commit/{commit}",
+            "{base}/src/commit/{commit}/{path}#L{line}",
+        ))
+    } else {
+        None
+    }
+}
+
+/// Web scheme, host and repository path of a remote URL, e.g. ("https", "github.com",
+/// "owner/repo") for "git@github.com:owner/repo.git"; None for local paths
+fn parse_remote_url(url: &str) -> Option<(&'static str, String, String)> {
+    let url = url.trim();
+    let (scheme, authority, path) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let scheme = match scheme {
+                "http" => "http",
+                "https" | "ssh" | "git" | "git+ssh" | "ssh+git" => "https",
+                _ => return None,
+            };
+            (scheme, authority, path)
+        }
+        // scp-like syntax: [user@]host:owner/repo.git
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            // "C:\repo" and "./dir:x" are local paths
+            if authority.len() < 2 || authority.contains(['/', '\\']) {
+                return None;
+            }
+            ("https", authority, path)
+        }
+    };
+
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    // Ports belong to the SSH or git server, not the web interface
+    let host = host.split(':').next().unwrap_or(host).to_lowercase();
+    let repo = path.trim_matches('/');
+    let repo = repo
+        .strip_suffix(".git")
+        .unwrap_or(repo)
+        .trim_end_matches('/');
+    if host.is_empty() || repo.is_empty() {
+        return None;
+    }
+    Some((scheme, host, repo.to_string()))
+}
+
+/// Wrap text in an OSC 8 terminal hyperlink
+pub fn hyperlink(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Whether stdout is a terminal known to support OSC 8 hyperlinks
+/// FORCE_HYPERLINK=1 or 0 overrides the guess
+pub fn terminal_supports_hyperlinks() -> bool {
+    let var = |name: &str| std::env::var(name).ok();
+    if let Some(force) = var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    if !std::io::stdout().is_terminal() {
+        return false;
+    }
+
+    let term = var("TERM").unwrap_or_default();
+    if term == "dumb" {
+        return false;
+    }
+    let known_program = var("TERM_PROGRAM").is_some_and(|program| {
+        matches!(
+            program.as_str(),
+            "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper" | "kitty"
+        )
+    });
+    let vte = var("VTE_VERSION")
+        .and_then(|version| version.parse::<u32>().ok())
+        .is_some_and(|version| version >= 5000);
+
+    known_program
+        || vte
+        || var("WT_SESSION").is_some()
+        || var("KONSOLE_VERSION").is_some()
+        || var("KITTY_WINDOW_ID").is_some()
+        || ["kitty", "alacritty", "foot", "ghostty", "wezterm"]
+            .iter()
+            .any(|name| term.contains(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote_url() {
+        let github = Some(("https", "github.com".to_string(), "owner/repo".to_string()));
+        assert_eq!(parse_remote_url("git@github.com:owner/repo.git"), github);
+        assert_eq!(
+            parse_remote_url("https://github.com/owner/repo.git"),
+            github
+        );
+        assert_eq!(
+            parse_remote_url("https://user@github.com/owner/repo/"),
+            github
+        );
+        assert_eq!(
+            parse_remote_url("ssh://git@gitlab.example.com:2222/group/sub/repo.git"),
+            Some((
+                "https",
+                "gitlab.example.com".to_string(),
+                "group/sub/repo".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_remote_url("http://gitea.local:3000/me/tool"),
+            Some(("http", "gitea.local".to_string(), "me/tool".to_string()))
+        );
+        assert_eq!(parse_remote_url("/srv/git/repo.git"), None);
+        assert_eq!(parse_remote_url("C:\\repos\\repo"), None);
+        assert_eq!(parse_remote_url("file:///srv/git/repo.git"), None);
+    }
+
+    #[test]
+    fn test_urls_per_host() {
+        let commit = Oid::from_str("61fcdda9f3c1e0f4b6b0b5a0c8f5bb0a1d2e3f45").unwrap();
+        let path = Path::new("src/my file.rs");
+
+        let github = RemoteLinks::new("git@github.com:owner/repo.git", None, None).unwrap();
+        assert_eq!(
+            github.commit_url(commit).unwrap(),
+            format!("https://github.com/owner/repo/commit/{}", commit)
+        );
+        assert_eq!(
+            github.file_url(commit, path, 12).unwrap(),
+            format!(
+                "https://github.com/owner/repo/blob/{}/src/my%20file.rs#L12",
+                commit
+            )
+        );
+
+        let gitlab = RemoteLinks::new("https://gitlab.com/group/repo", None, None).unwrap();
+        assert!(gitlab.commit_url(commit).unwrap().contains("/-/commit/"));
+        let bitbucket = RemoteLinks::new("git@bitbucket.org:team/repo.git", None, None).unwrap();
+        assert!(
+            bitbucket
+                .file_url(commit, path, 3)
+                .unwrap()
+                .ends_with("#lines-3")
+        );
+        let gitea = RemoteLinks::new("https://codeberg.org/me/repo.git", None, None).unwrap();
+        assert!(
+            gitea
+                .file_url(commit, path, 3)
+                .unwrap()
+                .contains("/src/commit/")
+        );
+
+        // Unknown hosts need a template
+        assert!(RemoteLinks::new("git@git.corp:team/repo.git", None, None).is_none());
+        let corp = RemoteLinks::new(
+            "git@git.corp:team/repo.git",
+            Some("https://review.corp/{repo}/+/{commit}".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            corp.commit_url(commit).unwrap(),
+            format!("https://review.corp/team/repo/+/{}", commit)
+        );
+        assert_eq!(corp.file_url(commit, path, 1), None);
+    }
+
+    #[test]
+    fn test_hyperlink() {
+        assert_eq!(
+            hyperlink("61fcdda", "https://x/y"),
+            "\x1b]8;;https://x/y\x1b\\61fcdda\x1b]8;;\x1b\\"
+        );
+    }
+}
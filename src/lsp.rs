@@ -0,0 +1,398 @@
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use crate::copies::CopyDetection;
+use crate::git::{Target, TargetPath};
+use crate::porcelain::line_origins;
+use crate::utils::{format_timestamp_day_month_year, percent_decode};
+
+/// JSON-RPC error code for requests the server doesn't implement
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Blame of one open document
+struct DocumentBlame {
+    /// Index into `commits` for every line
+    lines: Vec<usize>,
+    /// Length of every line in UTF-16 code units, where inlay hints are placed
+    line_lengths: Vec<usize>,
+    commits: Vec<CommitDetails>,
+}
+
+struct CommitDetails {
+    /// None for changes that aren't committed yet
+    hash: Option<String>,
+    author: String,
+    email: String,
+    date: String,
+    summary: String,
+    message: String,
+}
+
+impl CommitDetails {
+    /// Short form for code lenses and inlay hints
+    fn label(&self) -> String {
+        match &self.hash {
+            Some(_) => format!("{}, {} · {}", self.author, self.date, self.summary),
+            None => "Not committed yet".to_string(),
+        }
+    }
+
+    /// Markdown for hovers
+    fn markdown(&self) -> String {
+        match &self.hash {
+            Some(hash) => format!(
+                "**{}** {} <{}> · {}\n\n{}",
+                &hash[..7],
+                self.author,
+                self.email,
+                self.date,
+                self.message
+            ),
+            None => "**Not committed yet**".to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, DocumentBlame>,
+    /// Whether the client lets the server ask it to re-request code lenses and inlay hints
+    code_lens_refresh: bool,
+    inlay_hint_refresh: bool,
+    next_request_id: u64,
+    shutdown: bool,
+}
+
+/// Run a language server on stdin and stdout until the client sends "exit"
+/// Open files get a code lens on the first line of every blame hunk, an inlay hint with the
+/// blame of every line and a hover with the commit details; blame is recomputed on save
+pub fn run_lsp() -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut output = std::io::stdout().lock();
+    let mut server = Server::default();
+
+    while let Some(message) = read_message(&mut input)? {
+        if message.get("method").and_then(Value::as_str) == Some("exit") {
+            if server.shutdown {
+                return Ok(());
+            }
+            return Err(anyhow!("Received exit before shutdown"));
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl Server {
+    /// Handle one message, returning the responses and requests to send back
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to one of our refresh requests
+            return Vec::new();
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => Some(self.initialize(&params)),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "textDocument/codeLens" => Some(self.code_lenses(&params)),
+            "textDocument/inlayHint" => Some(self.inlay_hints(&params)),
+            "textDocument/hover" => Some(self.hover(&params)),
+            "textDocument/didOpen" => {
+                self.update(&params);
+                None
+            }
+            "textDocument/didSave" => {
+                self.update(&params);
+                return self.refresh_requests();
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = document_uri(&params) {
+                    self.documents.remove(uri);
+                }
+                None
+            }
+            _ => None,
+        };
+
+        // Notifications have no id and get no response
+        let Some(id) = id else {
+            return Vec::new();
+        };
+        vec![match result {
+            Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": METHOD_NOT_FOUND, "message": format!("Unsupported method: {}", method) }
+            }),
+        }]
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        let workspace = &params["capabilities"]["workspace"];
+        self.code_lens_refresh = workspace["codeLens"]["refreshSupport"].as_bool() == Some(true);
+        self.inlay_hint_refresh = workspace["inlayHint"]["refreshSupport"].as_bool() == Some(true);
+
+        json!({
+            "capabilities": {
+                "textDocumentSync": { "openClose": true, "change": 0, "save": true },
+                "codeLensProvider": { "resolveProvider": false },
+                "inlayHintProvider": true,
+                "hoverProvider": true
+            },
+            "serverInfo": { "name": "wer", "version": env!("CARGO_PKG_VERSION") }
+        })
+    }
+
+    /// Blame the saved file behind a document; files that can't be blamed get no annotations
+    fn update(&mut self, params: &Value) {
+        let Some(uri) = document_uri(params) else {
+            return;
+        };
+        match blame_document(uri) {
+            Ok(blame) => {
+                self.documents.insert(uri.to_string(), blame);
+            }
+            Err(e) => {
+                eprintln!("wer lsp: {}: {}", uri, e);
+                self.documents.remove(uri);
+            }
+        }
+    }
+
+    /// Ask the client to re-request annotations, when it said it supports that
+    fn refresh_requests(&mut self) -> Vec<Value> {
+        let mut requests = Vec::new();
+        for (supported, method) in [
+            (self.code_lens_refresh, "workspace/codeLens/refresh"),
+            (self.inlay_hint_refresh, "workspace/inlayHint/refresh"),
+        ] {
+            if supported {
+                self.next_request_id += 1;
+                requests.push(json!({
+                    "jsonrpc": "2.0",
+                    "id": format!("wer-{}", self.next_request_id),
+                    "method": method
+                }));
+            }
+        }
+        requests
+    }
+
+    fn document(&mut self, params: &Value) -> Option<&DocumentBlame> {
+        let uri = document_uri(params)?;
+        if !self.documents.contains_key(uri) {
+            self.update(params);
+        }
+        self.documents.get(uri)
+    }
+
+    /// A lens above the first line of every run of lines from the same commit
+    fn code_lenses(&mut self, params: &Value) -> Value {
+        let Some(document) = self.document(params) else {
+            return json!([]);
+        };
+
+        let mut lenses = Vec::new();
+        let mut previous = None;
+        for (line, &commit) in document.lines.iter().enumerate() {
+            if previous != Some(commit) {
+                let position = json!({ "line": line, "character": 0 });
+                lenses.push(json!({
+                    "range": { "start": position, "end": position },
+                    "command": { "title": document.commits[commit].label(), "command": "" }
+                }));
+            }
+            previous = Some(commit);
+        }
+        Value::Array(lenses)
+    }
+
+    /// A hint after the end of every line in the requested range
+    fn inlay_hints(&mut self, params: &Value) -> Value {
+        let range = &params["range"];
+        let start = range["start"]["line"].as_u64().unwrap_or(0) as usize;
+        let end = range["end"]["line"].as_u64().unwrap_or(u64::MAX) as usize;
+        let Some(document) = self.document(params) else {
+            return json!([]);
+        };
+
+        let hints: Vec<Value> = document
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(line, _)| *line >= start && *line <= end)
+            .map(|(line, &commit)| {
+                json!({
+                    "position": { "line": line, "character": document.line_lengths[line] },
+                    "label": document.commits[commit].label(),
+                    "paddingLeft": true
+                })
+            })
+            .collect();
+        Value::Array(hints)
+    }
+
+    fn hover(&mut self, params: &Value) -> Value {
+        let line = params["position"]["line"].as_u64().unwrap_or(0) as usize;
+        let Some(document) = self.document(params) else {
+            return Value::Null;
+        };
+        let Some(&commit) = document.lines.get(line) else {
+            return Value::Null;
+        };
+
+        json!({
+            "contents": { "kind": "markdown", "value": document.commits[commit].markdown() }
+        })
+    }
+}
+
+fn document_uri(params: &Value) -> Option<&str> {
+    params["textDocument"]["uri"].as_str()
+}
+
+fn blame_document(uri: &str) -> Result<DocumentBlame> {
+    let path = uri_to_path(uri)?;
+    let target = Target::open(&TargetPath::Workdir(path), true)?;
+    let repo = &target.repo;
+    let content = target.read_content()?;
+    let origins = line_origins(
+        &target,
+        &content,
+        1,
+        content.lines().count(),
+        CopyDetection::default(),
+    )?;
+
+    let mut commits: Vec<CommitDetails> = Vec::new();
+    let mut indices = HashMap::new();
+    let mut lines = Vec::new();
+    for origin in origins {
+        let index = match indices.get(&origin.commit_id) {
+            Some(&index) => index,
+            None => {
+                let details = if origin.commit_id.is_zero() {
+                    CommitDetails {
+                        hash: None,
+                        author: String::new(),
+                        email: String::new(),
+                        date: String::new(),
+                        summary: String::new(),
+                        message: String::new(),
+                    }
+                } else {
+                    let commit = repo.find_commit(origin.commit_id)?;
+                    let author = commit.author();
+                    CommitDetails {
+                        hash: Some(commit.id().to_string()),
+                        author: author.name().unwrap_or("Unknown").to_string(),
+                        email: author.email().unwrap_or("").to_string(),
+                        date: format_timestamp_day_month_year(commit.time().seconds()),
+                        summary: commit.summary().unwrap_or("").to_string(),
+                        message: commit.message().unwrap_or("").trim_end().to_string(),
+                    }
+                };
+                commits.push(details);
+                indices.insert(origin.commit_id, commits.len() - 1);
+                commits.len() - 1
+            }
+        };
+        lines.push(index);
+    }
+
+    Ok(DocumentBlame {
+        lines,
+        line_lengths: content
+            .lines()
+            .map(|line| line.encode_utf16().count())
+            .collect(),
+        commits,
+    })
+}
+
+/// Local path of a file:// URI, with percent-escapes decoded
+fn uri_to_path(uri: &str) -> Result<String> {
+    let encoded = uri
+        .strip_prefix("file://")
+        .ok_or_else(|| anyhow!("Only file:// URIs are supported: {}", uri))?;
+    // Windows paths look like /C:/dir/file
+    let encoded = match encoded.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &encoded[1..],
+        _ => encoded,
+    };
+
+    percent_decode(encoded)
+}
+
+/// Read one message framed with a Content-Length header; None at the end of input
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            content_length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+
+    let length = content_length.ok_or_else(|| anyhow!("Message without Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri_to_path() {
+        assert_eq!(
+            uri_to_path("file:///home/me/my%20project/a.rs").unwrap(),
+            "/home/me/my project/a.rs"
+        );
+        assert_eq!(
+            uri_to_path("file:///C:/src/main.rs").unwrap(),
+            "C:/src/main.rs"
+        );
+        assert!(uri_to_path("untitled:Untitled-1").is_err());
+    }
+
+    #[test]
+    fn test_message_framing() {
+        let mut output = Vec::new();
+        write_message(&mut output, &json!({ "id": 1 })).unwrap();
+        assert_eq!(output, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+
+        let mut input = &output[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "id": 1 })));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+}
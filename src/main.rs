@@ -1,18 +1,62 @@
 use anyhow::Result;
-use clap::Parser;
+use std::io::{BufRead, IsTerminal, Write};
 
+mod busfactor;
 mod cli;
+mod codeowners;
+mod completions;
+mod copies;
 mod git;
+mod history;
+mod hotspots;
+mod html;
+mod links;
+mod lsp;
+mod picker;
+mod porcelain;
+mod refs;
+mod reviewers;
 mod search;
+mod serve;
 mod syntax;
+mod table;
+#[cfg(test)]
+mod test_utils;
+mod timeline;
 mod utils;
+mod watch;
 
-use cli::Cli;
-use git::{get_blame, get_last_commit};
-use search::find_all_matches;
+use busfactor::get_bus_factor;
+use cli::{Cli, CodeownersAction, Command, Format};
+use codeowners::{check_codeowners, suggest_codeowners};
+use completions::{completion_script, man_page, print_path_completions};
+use git::{
+    Origin, TargetPath, get_blame, get_last_commit, get_last_commit_all_branches, tree_target,
+};
+use history::get_line_history;
+use hotspots::get_hotspots;
+use html::get_blame_html;
+use lsp::run_lsp;
+use picker::{pick_match, select_match};
+use porcelain::get_blame_porcelain;
+use reviewers::suggest_reviewers;
+use search::{find_all_matches, find_tree_matches};
+use serve::serve;
+use table::{blame_headers, blame_rows, commit_headers, last_commit_rows, render_table};
+use timeline::get_timeline;
+use utils::{parse_line_range, parse_since};
+use watch::{watch_blame, watch_last_commit};
 
 fn main() {
-    let cli = Cli::parse();
+    // The completion scripts ask `wer __complete PREFIX` for path candidates; it is handled
+    // before clap so it stays out of the help and out of the generated scripts themselves
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("__complete") {
+        print_path_completions(args.get(2).map_or("", String::as_str));
+        return;
+    }
+
+    let cli = Cli::parse_checked();
 
     match run(cli) {
         Ok(()) => {}
@@ -31,45 +75,266 @@ fn run(cli: Cli) -> Result<()> {
         ));
     }
 
-    // Validate that --last only works in normal mode (not blame mode)
-    if cli.last.is_some() && cli.blame {
+    let origin = if cli.both {
+        Origin::Both
+    } else if cli.created {
+        Origin::Created
+    } else {
+        Origin::LastEdit
+    };
+
+    // --line-history FILE:LINES is shorthand for -L LINES --history FILE
+    let mut paths = cli.paths.clone();
+    let mut line_range = cli.lines.as_deref().map(parse_line_range).transpose()?;
+    if let Some(spec) = &cli.line_history {
+        let (file, lines) = spec
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow::anyhow!("--line-history expects FILE:LINES, e.g. main.rs:42"))?;
+        paths = vec![file.to_string()];
+        line_range = Some(parse_line_range(lines)?);
+    }
+    let history = cli.history || cli.line_history.is_some();
+
+    if line_range.is_some() && !cli.blame && !history {
+        return Err(anyhow::anyhow!("-L only works with --blame or --history"));
+    }
+
+    // -C: behave as if wer was started in the given directory
+    if let Some(dir) = &cli.directory {
+        std::env::set_current_dir(dir)
+            .map_err(|e| anyhow::anyhow!("Cannot change to directory '{}': {}", dir, e))?;
+    }
+
+    if let Some(command) = &cli.command {
+        let output = match command {
+            Command::Codeowners { .. } if cli.format != Format::Text => {
+                return Err(anyhow::anyhow!(
+                    "wer codeowners only prints text and cannot be combined with --format"
+                ));
+            }
+            Command::Reviewers { range, max } => {
+                if cli.format != Format::Text {
+                    return Err(anyhow::anyhow!(
+                        "wer reviewers only prints text and cannot be combined with --format"
+                    ));
+                }
+                suggest_reviewers(cli.git_dir.as_deref(), range.as_deref(), *max, cli.no_color)?
+            }
+            Command::BusFactor { dir, months } => {
+                let target = directory_target(&cli, dir.as_deref())?;
+                get_bus_factor(&target, *months, cli.format, cli.no_color)?
+            }
+            Command::Hotspots {
+                dir,
+                since,
+                sort,
+                by_size,
+                max,
+            } => {
+                let target = directory_target(&cli, dir.as_deref())?;
+                let now = chrono::Utc::now().timestamp();
+                let since = since
+                    .as_deref()
+                    .map(|since| parse_since(since, now))
+                    .transpose()?;
+                get_hotspots(
+                    &target,
+                    since,
+                    *sort,
+                    *by_size,
+                    *max,
+                    cli.format,
+                    cli.no_color,
+                )?
+            }
+            Command::Codeowners {
+                action: CodeownersAction::Check { months },
+            } => check_codeowners(cli.git_dir.as_deref(), *months, cli.no_color)?,
+            Command::Codeowners {
+                action:
+                    CodeownersAction::Suggest {
+                        months,
+                        depth,
+                        owners,
+                    },
+            } => suggest_codeowners(cli.git_dir.as_deref(), *months, *depth, *owners)?,
+            // Servers, scripts and the man page write their own output
+            Command::Serve { port, host } => {
+                serve(cli.git_dir.as_deref(), host, *port)?;
+                return Ok(());
+            }
+            Command::Lsp => {
+                run_lsp()?;
+                return Ok(());
+            }
+            Command::Completions { shell } => {
+                print!("{}", completion_script(*shell));
+                return Ok(());
+            }
+            Command::Man => {
+                print!("{}", man_page()?);
+                return Ok(());
+            }
+        };
+        println!("{}\n", output);
+        return Ok(());
+    }
+
+    match cli.format {
+        Format::Text => {}
+        Format::Html if cli.blame => {}
+        Format::Html => return Err(anyhow::anyhow!("--format html only works with --blame")),
+        Format::Json | Format::Csv | Format::Tsv | Format::Markdown => {
+            if history || cli.timeline || cli.all_branches || cli.created || cli.both {
+                return Err(anyhow::anyhow!(
+                    "--format json, csv, tsv and markdown only work in normal mode (including --last) and with --blame"
+                ));
+            }
+        }
+    }
+
+    // Collect the requested paths from the arguments and, if asked, from stdin
+    let mut inputs = paths;
+    if cli.stdin {
+        inputs.extend(read_stdin_paths()?);
+    }
+    if inputs.is_empty() {
+        if cli.stdin {
+            return Err(anyhow::anyhow!("No paths were provided on stdin"));
+        }
+        inputs.push(".".to_string());
+    }
+
+    // Resolve each input - either search for it or use it directly
+    let mut target_paths: Vec<TargetPath> = Vec::new();
+    let mut failures = 0;
+    for input_path in inputs.iter() {
+        match resolve_input(&cli, input_path) {
+            Ok(matches) => {
+                for path in matches {
+                    if !target_paths.contains(&path) {
+                        target_paths.push(path);
+                    }
+                }
+            }
+            // A single bad path in a list shouldn't hide the results for the others
+            Err(e) if inputs.len() > 1 => {
+                eprintln!("Error: {}", e);
+                failures += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // An HTML page can only hold one file
+    if cli.format == Format::Html && target_paths.len() > 1 {
         return Err(anyhow::anyhow!(
-            "--last flag only works in normal mode, not with --blame"
+            "--format html exports one file at a time, but {} paths were given",
+            target_paths.len()
+        ));
+    }
+    let copies = cli.copy_detection();
+    if cli.format == Format::Html && copies.is_enabled() {
+        return Err(anyhow::anyhow!(
+            "--detect-moves and --detect-copies cannot be combined with --format html"
         ));
     }
 
-    // Resolve the path(s) - either search for it or use current directory
-    let target_paths = if let Some(input_path) = cli.path {
-        let matches = find_all_matches(&input_path)?;
+    let porcelain = cli.porcelain || cli.line_porcelain;
+    if porcelain {
+        if cli.format != Format::Text {
+            return Err(anyhow::anyhow!(
+                "--porcelain and --line-porcelain cannot be combined with --format"
+            ));
+        }
+        // Like git blame, porcelain output covers exactly one file
+        if target_paths.len() > 1 {
+            return Err(anyhow::anyhow!(
+                "--porcelain blames one file at a time, but {} paths were given",
+                target_paths.len()
+            ));
+        }
+        if let Some(target_path) = target_paths.first() {
+            print!(
+                "{}",
+                get_blame_porcelain(target_path, line_range, cli.line_porcelain, copies)?
+            );
+        }
+        return Ok(());
+    }
 
-        // In blame mode, only allow single file
-        if cli.blame && matches.len() > 1 {
-            let mut error_msg =
-                format!("Multiple files/directories named '{}' found:\n", input_path);
-            for (i, path) in matches.iter().enumerate() {
-                error_msg.push_str(&format!("  {}. {}\n", i + 1, path));
-            }
-            error_msg.push_str("\nBlame mode only works with a single file. Please specify the full path to the desired file.");
-            return Err(anyhow::anyhow!(error_msg));
+    if cli.watch {
+        if cli.format != Format::Text {
+            return Err(anyhow::anyhow!("--watch cannot be combined with --format"));
         }
+        let [target_path] = target_paths.as_slice() else {
+            return Err(anyhow::anyhow!(
+                "--watch follows one path at a time, but {} paths were given",
+                target_paths.len()
+            ));
+        };
+        return if cli.blame {
+            watch_blame(target_path, cli.output_options(), line_range)
+        } else {
+            watch_last_commit(
+                target_path,
+                cli.output_options(),
+                cli.last,
+                cli.recurse_submodules,
+                origin,
+            )
+        };
+    }
 
-        matches
-    } else {
-        vec![".".to_string()]
-    };
+    if matches!(
+        cli.format,
+        Format::Json | Format::Csv | Format::Tsv | Format::Markdown
+    ) {
+        return print_table(&cli, &target_paths, line_range, failures);
+    }
 
     // Process and print each target path
     for target_path in target_paths.iter() {
-        let output = if cli.blame {
-            get_blame(target_path, cli.no_color, cli.date_only, cli.commit_message)?
-        } else {
-            get_last_commit(
+        let result = if let (true, Some(line_range)) = (history, line_range) {
+            get_line_history(
+                target_path,
+                cli.no_color,
+                cli.commit_message,
+                cli.links,
+                line_range,
+            )
+        } else if cli.blame && cli.format == Format::Html {
+            get_blame_html(target_path, line_range)
+        } else if cli.blame {
+            get_blame(target_path, cli.output_options(), line_range, None)
+        } else if cli.timeline {
+            get_timeline(target_path, cli.period, cli.by_author, cli.no_color)
+        } else if cli.all_branches {
+            get_last_commit_all_branches(
                 target_path,
                 cli.no_color,
                 cli.date_only,
                 cli.commit_message,
+                cli.links,
+            )
+        } else {
+            get_last_commit(
+                target_path,
+                cli.output_options(),
                 cli.last,
-            )?
+                cli.recurse_submodules,
+                origin,
+            )
+        };
+
+        let output = match result {
+            Ok(output) => output,
+            Err(e) if target_paths.len() > 1 => {
+                eprintln!("Error: {}: {}", target_path, e);
+                failures += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
         };
 
         if target_paths.len() > 1 {
@@ -79,5 +344,148 @@ fn run(cli: Cli) -> Result<()> {
         }
     }
 
+    if failures > 0 {
+        return Err(anyhow::anyhow!(
+            "{} path(s) could not be processed",
+            failures
+        ));
+    }
+
+    Ok(())
+}
+
+/// Print all targets as one table, with a leading path column when there are several
+fn print_table(
+    cli: &Cli,
+    target_paths: &[TargetPath],
+    line_range: Option<(usize, usize)>,
+    mut failures: usize,
+) -> Result<()> {
+    let mut headers = if cli.blame {
+        blame_headers(cli.date_only, cli.copy_detection())
+    } else {
+        commit_headers(cli.date_only)
+    };
+    let multiple = target_paths.len() > 1;
+    if multiple {
+        headers.insert(0, "path");
+    }
+
+    let mut rows = Vec::new();
+    for target_path in target_paths {
+        let result = if cli.blame {
+            blame_rows(target_path, cli.date_only, line_range, cli.copy_detection())
+        } else {
+            last_commit_rows(target_path, cli.date_only, cli.last)
+        };
+
+        match result {
+            Ok(target_rows) => rows.extend(target_rows.into_iter().map(|mut row| {
+                if multiple {
+                    row.insert(0, target_path.to_string().into());
+                }
+                row
+            })),
+            Err(e) if multiple => {
+                eprintln!("Error: {}: {}", target_path, e);
+                failures += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Output piped into a command like "head" may be closed early, which isn't an error
+    let mut stdout = std::io::stdout().lock();
+    match writeln!(stdout, "{}", render_table(&headers, &rows, cli.format))
+        .and_then(|()| stdout.flush())
+    {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+        _ => {}
+    }
+
+    if failures > 0 {
+        return Err(anyhow::anyhow!(
+            "{} path(s) could not be processed",
+            failures
+        ));
+    }
+
     Ok(())
 }
+
+/// The directory a subcommand reports on, read from the tree with --git-dir or in a bare repository
+fn directory_target(cli: &Cli, dir: Option<&str>) -> Result<TargetPath> {
+    let dir = dir.unwrap_or(".");
+    Ok(tree_target(dir, cli.git_dir.as_deref(), None)?
+        .unwrap_or_else(|| TargetPath::Workdir(dir.to_string())))
+}
+
+/// Find all target paths an input may refer to, in a repository tree or on disk
+fn find_targets(cli: &Cli, input_path: &str) -> Result<Vec<TargetPath>> {
+    if let Some(TargetPath::Tree { git_dir, rev, path }) =
+        tree_target(input_path, cli.git_dir.as_deref(), cli.rev.as_deref())?
+    {
+        let repo = git2::Repository::open(&git_dir)?;
+        return Ok(find_tree_matches(&repo, rev.as_deref(), &path)?
+            .into_iter()
+            .map(|path| TargetPath::Tree {
+                git_dir: git_dir.clone(),
+                rev: rev.clone(),
+                path,
+            })
+            .collect());
+    }
+
+    Ok(find_all_matches(input_path)?
+        .into_iter()
+        .map(TargetPath::Workdir)
+        .collect())
+}
+
+/// Resolve a single input path to the target path(s) it refers to
+fn resolve_input(cli: &Cli, input_path: &str) -> Result<Vec<TargetPath>> {
+    let matches = find_targets(cli, input_path)?;
+
+    if matches.len() <= 1 {
+        return Ok(matches);
+    }
+
+    if let Some(pick) = cli.pick {
+        return Ok(vec![select_match(input_path, &matches, pick)?]);
+    }
+
+    // Blame and line history need a single file
+    let needs_file = cli.blame || cli.history || cli.line_history.is_some();
+    if !needs_file || cli.all {
+        return Ok(matches);
+    }
+
+    // Ask which file to use if we can
+    if std::io::stdin().is_terminal() {
+        return Ok(vec![pick_match(input_path, &matches)?]);
+    }
+
+    let mut error_msg = format!("Multiple files/directories named '{}' found:\n", input_path);
+    for (i, path) in matches.iter().enumerate() {
+        error_msg.push_str(&format!("  {}. {}\n", i + 1, path));
+    }
+    if cli.blame {
+        error_msg.push_str("\nBlame mode only works with a single file. Please specify the full path, use --pick N to choose a match, or --all to blame every match.");
+    } else {
+        error_msg.push_str("\nLine history only works with a single file. Please specify the full path or use --pick N to choose a match.");
+    }
+    Err(anyhow::anyhow!(error_msg))
+}
+
+/// Read newline-separated paths from stdin, skipping blank lines
+fn read_stdin_paths() -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            paths.push(line.to_string());
+        }
+    }
+    Ok(paths)
+}
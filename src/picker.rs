@@ -0,0 +1,96 @@
+use anyhow::{Result, anyhow};
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+
+/// Ask the user to choose one of several matching paths
+/// Prints a numbered list to stderr and reads the selection from stdin, re-prompting on invalid input
+pub fn pick_match<T: Display + Clone>(input_path: &str, matches: &[T]) -> Result<T> {
+    let stdin = std::io::stdin();
+    let mut stderr = std::io::stderr();
+
+    writeln!(
+        stderr,
+        "Multiple files/directories named '{}' found:",
+        input_path
+    )?;
+    for (i, path) in matches.iter().enumerate() {
+        writeln!(stderr, "  {}. {}", i + 1, path)?;
+    }
+
+    loop {
+        write!(stderr, "Select a file [1-{}] (q to quit): ", matches.len())?;
+        stderr.flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Err(anyhow!("No selection made"));
+        }
+
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("q") {
+            return Err(anyhow!("Selection cancelled"));
+        }
+
+        match parse_selection(line, matches.len()) {
+            Ok(index) => return Ok(matches[index].clone()),
+            Err(e) => writeln!(stderr, "{}", e)?,
+        }
+    }
+}
+
+/// Select a match by its 1-based position, as shown in the numbered match list
+pub fn select_match<T: Clone>(input_path: &str, matches: &[T], pick: usize) -> Result<T> {
+    if pick == 0 || pick > matches.len() {
+        return Err(anyhow!(
+            "--pick {} is out of range: '{}' has {} matches",
+            pick,
+            input_path,
+            matches.len()
+        ));
+    }
+
+    Ok(matches[pick - 1].clone())
+}
+
+/// Parse a 1-based selection number and return the corresponding 0-based index
+fn parse_selection(input: &str, count: usize) -> Result<usize> {
+    let number: usize = input
+        .parse()
+        .map_err(|_| anyhow!("'{}' is not a number", input))?;
+
+    if number == 0 || number > count {
+        return Err(anyhow!("Please enter a number between 1 and {}", count));
+    }
+
+    Ok(number - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selection_valid() {
+        assert_eq!(parse_selection("1", 3).unwrap(), 0);
+        assert_eq!(parse_selection("3", 3).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_parse_selection_out_of_range() {
+        assert!(parse_selection("0", 3).is_err());
+        assert!(parse_selection("4", 3).is_err());
+    }
+
+    #[test]
+    fn test_parse_selection_not_a_number() {
+        let result = parse_selection("abc", 3);
+        assert!(result.unwrap_err().to_string().contains("not a number"));
+    }
+
+    #[test]
+    fn test_select_match() {
+        let matches = vec!["a/file.rs".to_string(), "b/file.rs".to_string()];
+        assert_eq!(select_match("file.rs", &matches, 2).unwrap(), "b/file.rs");
+        assert!(select_match("file.rs", &matches, 3).is_err());
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::{Result, anyhow};
+use git2::{Oid, Repository, Signature};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::copies::CopyDetection;
+use crate::git::{Target, TargetPath, blame_at};
+use crate::utils::shown_lines;
+
+/// Blame in the format of `git blame --porcelain`, or `--line-porcelain` when `every_line`
+/// Commit details follow the first line of a commit (every line with `every_line`), and
+/// uncommitted lines in the working copy are attributed to "Not Committed Yet" like git does
+pub fn get_blame_porcelain(
+    path: &TargetPath,
+    line_range: Option<(usize, usize)>,
+    every_line: bool,
+    copies: CopyDetection,
+) -> Result<String> {
+    let target = Target::open(path, true)?;
+    let repo = &target.repo;
+
+    let content = target.read_content()?;
+    let lines: Vec<&str> = content.lines().collect();
+
+    let (start, end) = shown_lines(path, line_range, lines.len())?;
+
+    let origins = line_origins(&target, &content, start, end, copies)?;
+
+    let mut result = String::new();
+    let mut shown: HashSet<Oid> = HashSet::new();
+    let mut index = 0;
+    while index < origins.len() {
+        // A group is a run of consecutive lines from the same commit and file
+        let origin = &origins[index];
+        let mut group_end = index + 1;
+        while group_end < origins.len()
+            && origins[group_end].commit_id == origin.commit_id
+            && origins[group_end].orig_path == origin.orig_path
+            && origins[group_end].orig_line == origin.orig_line + (group_end - index)
+        {
+            group_end += 1;
+        }
+        let details = commit_details(
+            repo,
+            origin.commit_id,
+            &origin.orig_path,
+            origin.boundary,
+            &target,
+        )?;
+
+        for offset in 0..group_end - index {
+            let line_num = start + index + offset;
+            result.push_str(&format!(
+                "{} {} {}",
+                origin.commit_id,
+                origin.orig_line + offset,
+                line_num
+            ));
+            if offset == 0 {
+                result.push_str(&format!(" {}", group_end - index));
+            }
+            result.push('\n');
+
+            if every_line || shown.insert(origin.commit_id) {
+                result.push_str(&details);
+            }
+            result.push('\t');
+            result.push_str(lines[line_num - 1]);
+            result.push('\n');
+        }
+
+        index = group_end;
+    }
+
+    Ok(result)
+}
+
+/// Where a line of a file came from
+pub struct LineOrigin {
+    /// Commit that last changed the line, zero when the change isn't committed yet
+    pub commit_id: Oid,
+    /// Line number in that commit's version of the file
+    pub orig_line: usize,
+    /// Path of the file in that commit
+    pub orig_path: PathBuf,
+    /// Whether the commit is where blame stopped looking (a root commit)
+    pub boundary: bool,
+}
+
+/// Blame lines `start` to `end` of a target's content
+/// For a file in the working copy, `content` is compared to the last commit, and lines changed
+/// since are attributed to the zero commit, and moved or copied lines to where they came from
+/// as far as `copies` looks
+pub fn line_origins(
+    target: &Target,
+    content: &str,
+    start: usize,
+    end: usize,
+    copies: CopyDetection,
+) -> Result<Vec<LineOrigin>> {
+    let repo = &target.repo;
+    let blame = blame_at(repo, &target.relative_path, target.start, copies).map_err(|e| {
+        anyhow!(
+            "Failed to get blame for file '{}': {}",
+            target.relative_path.display(),
+            e
+        )
+    })?;
+    let sources = copies.line_sources(repo, &blame, &target.relative_path)?;
+    let line_count = content.lines().count();
+
+    let committed_lines = if target.full_path.is_some() && target.start.is_none() {
+        let head = repo.head()?.peel_to_commit()?;
+        let blob = head
+            .tree()?
+            .get_path(&target.relative_path)?
+            .to_object(repo)?
+            .peel_to_blob()?;
+        map_lines(blob.content(), content.as_bytes(), line_count)?
+    } else {
+        (1..=line_count).map(Some).collect()
+    };
+
+    let mut origins = Vec::new();
+    for line_num in start..=end.min(line_count) {
+        let origin = match committed_lines[line_num - 1] {
+            Some(committed_line) => {
+                let hunk = blame.get_line(committed_line).ok_or_else(|| {
+                    anyhow!(
+                        "No blame information for line {} of '{}'",
+                        line_num,
+                        target.relative_path.display()
+                    )
+                })?;
+                let (commit_id, orig_line, orig_path) = match sources.get(&committed_line) {
+                    Some(source) => (source.commit_id, source.line, source.path.clone()),
+                    None => (
+                        hunk.final_commit_id(),
+                        hunk.orig_start_line() + committed_line - hunk.final_start_line(),
+                        hunk.path()
+                            .map(Path::to_path_buf)
+                            .unwrap_or_else(|| target.relative_path.clone()),
+                    ),
+                };
+                // Like git blame without --root, root commits count as boundaries
+                let boundary = (hunk.is_boundary() && commit_id == hunk.final_commit_id())
+                    || repo.find_commit(commit_id)?.parent_count() == 0;
+                LineOrigin {
+                    commit_id,
+                    orig_line,
+                    orig_path,
+                    boundary,
+                }
+            }
+            None => LineOrigin {
+                commit_id: Oid::zero(),
+                orig_line: line_num,
+                orig_path: target.relative_path.clone(),
+                boundary: false,
+            },
+        };
+        origins.push(origin);
+    }
+
+    Ok(origins)
+}
+
+/// The header lines git prints after a commit's first line: author, committer, summary,
+/// boundary, the previous version of the file and the file name
+fn commit_details(
+    repo: &Repository,
+    commit_id: Oid,
+    orig_path: &Path,
+    boundary: bool,
+    target: &Target,
+) -> Result<String> {
+    let mut details = String::new();
+
+    if commit_id.is_zero() {
+        let now = chrono::Local::now();
+        let time = now.timestamp();
+        let tz = format_tz(now.offset().local_minus_utc() / 60);
+        let name = target.relative_path.display();
+        for role in ["author", "committer"] {
+            details.push_str(&format!(
+                "{role} Not Committed Yet\n{role}-mail <not.committed.yet>\n{role}-time {time}\n{role}-tz {tz}\n"
+            ));
+        }
+        details.push_str(&format!("summary Version of {} from {}\n", name, name));
+        if let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) {
+            details.push_str(&format!("previous {} {}\n", head.id(), name));
+        }
+        details.push_str(&format!("filename {}\n", name));
+        return Ok(details);
+    }
+
+    let commit = repo.find_commit(commit_id)?;
+    details.push_str(&format_signature("author", &commit.author()));
+    details.push_str(&format_signature("committer", &commit.committer()));
+    details.push_str(&format!("summary {}\n", commit.summary().unwrap_or("")));
+    if boundary {
+        details.push_str("boundary\n");
+    }
+    if let Some((parent_id, previous_path)) = previous_version(repo, &commit, orig_path)? {
+        details.push_str(&format!(
+            "previous {} {}\n",
+            parent_id,
+            previous_path.display()
+        ));
+    }
+    details.push_str(&format!("filename {}\n", orig_path.display()));
+
+    Ok(details)
+}
+
+fn format_signature(role: &str, signature: &Signature) -> String {
+    let when = signature.when();
+    format!(
+        "{role} {}\n{role}-mail <{}>\n{role}-time {}\n{role}-tz {}\n",
+        signature.name().unwrap_or(""),
+        signature.email().unwrap_or(""),
+        when.seconds(),
+        format_tz(when.offset_minutes())
+    )
+}
+
+/// Offset from UTC as git writes it, e.g. "+0200" or "-0530"
+fn format_tz(offset_minutes: i32) -> String {
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let minutes = offset_minutes.abs();
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// For every line of `new`, the number of the same line in `old`, or None when it was added
+pub fn map_lines(old: &[u8], new: &[u8], new_lines: usize) -> Result<Vec<Option<usize>>> {
+    let mut options = git2::DiffOptions::new();
+    options.context_lines(0);
+    let patch = git2::Patch::from_buffers(old, None, new, None, Some(&mut options))?;
+
+    let mut added = HashSet::new();
+    let mut removed = HashSet::new();
+    for hunk_index in 0..patch.num_hunks() {
+        for line_index in 0..patch.num_lines_in_hunk(hunk_index)? {
+            let line = patch.line_in_hunk(hunk_index, line_index)?;
+            match line.origin() {
+                '+' => added.extend(line.new_lineno()),
+                '-' => removed.extend(line.old_lineno()),
+                _ => {}
+            }
+        }
+    }
+
+    let mut old_line = 1;
+    let mut mapping = Vec::with_capacity(new_lines);
+    for new_line in 1..=new_lines as u32 {
+        if added.contains(&new_line) {
+            mapping.push(None);
+            continue;
+        }
+        while removed.contains(&old_line) {
+            old_line += 1;
+        }
+        mapping.push(Some(old_line as usize));
+        old_line += 1;
+    }
+
+    Ok(mapping)
+}
+
+/// The first parent and the file's path there, following a rename, when the file existed before
+fn previous_version(
+    repo: &Repository,
+    commit: &git2::Commit,
+    path: &Path,
+) -> Result<Option<(Oid, PathBuf)>> {
+    let Ok(parent) = commit.parent(0) else {
+        return Ok(None);
+    };
+    let parent_tree = parent.tree()?;
+    if parent_tree.get_path(path).is_ok() {
+        return Ok(Some((parent.id(), path.to_path_buf())));
+    }
+
+    let mut diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree()?), None)?;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+    let renamed_from = diff
+        .deltas()
+        .find(|delta| {
+            delta.status() == git2::Delta::Renamed && delta.new_file().path() == Some(path)
+        })
+        .and_then(|delta| delta.old_file().path().map(Path::to_path_buf));
+
+    Ok(renamed_from.map(|old_path| (parent.id(), old_path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_format_tz() {
+        assert_eq!(format_tz(120), "+0200");
+        assert_eq!(format_tz(-330), "-0530");
+        assert_eq!(format_tz(0), "+0000");
+    }
+
+    #[test]
+    fn test_porcelain_matches_git_layout() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit_files(&repo, &[("f.txt", "a\nb\nc\n")], "Alice", 1749456964, "one");
+        let second = commit_files(
+            &repo,
+            &[("f.txt", "a\nB\nc\n")],
+            "Bob",
+            1749556964,
+            "two\n\nbody",
+        );
+        // An uncommitted line at the end
+        std::fs::write(dir.path().join("f.txt"), "a\nB\nc\nd\n").unwrap();
+
+        let target = TargetPath::Workdir(dir.path().join("f.txt").to_string_lossy().to_string());
+        let output = get_blame_porcelain(&target, None, false, CopyDetection::default()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], format!("{} 1 1 1", first));
+        assert_eq!(lines[1], "author Alice");
+        assert_eq!(lines[2], "author-mail <alice@example.com>");
+        assert_eq!(lines[3], "author-time 1749456964");
+        assert_eq!(lines[4], "author-tz +0000");
+        assert_eq!(lines[9], "summary one");
+        assert_eq!(lines[10], "boundary");
+        assert_eq!(lines[11], "filename f.txt");
+        assert_eq!(lines[12], "\ta");
+
+        assert_eq!(lines[13], format!("{} 2 2 1", second));
+        assert_eq!(lines[22], "summary two");
+        assert_eq!(lines[23], format!("previous {} f.txt", first));
+        assert_eq!(lines[24], "filename f.txt");
+        assert_eq!(lines[25], "\tB");
+
+        // Alice's details were already shown, so only the line header follows
+        assert_eq!(lines[26], format!("{} 3 3 1", first));
+        assert_eq!(lines[27], "\tc");
+
+        assert_eq!(lines[28], format!("{} 4 4 1", Oid::zero()));
+        assert_eq!(lines[29], "author Not Committed Yet");
+        assert_eq!(lines[37], "summary Version of f.txt from f.txt");
+        assert_eq!(lines[38], format!("previous {} f.txt", second));
+        assert_eq!(lines[40], "\td");
+        assert_eq!(lines.len(), 41);
+
+        // --line-porcelain repeats the details on every line
+        let output =
+            get_blame_porcelain(&target, Some((1, 3)), true, CopyDetection::default()).unwrap();
+        assert_eq!(output.matches("author-mail <alice@example.com>").count(), 2);
+        assert_eq!(output.matches("\n\t").count(), 3);
+    }
+
+    #[test]
+    fn test_porcelain_of_empty_file() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(&repo, &[("empty", "")], "Alice", 1749456964, "add empty");
+        let target = TargetPath::Workdir(dir.path().join("empty").to_string_lossy().to_string());
+
+        // Like git blame, an empty file has nothing to report
+        let output = get_blame_porcelain(&target, None, false, CopyDetection::default()).unwrap();
+        assert_eq!(output, "");
+        assert!(
+            get_blame_porcelain(&target, Some((1, 1)), false, CopyDetection::default()).is_err()
+        );
+    }
+}
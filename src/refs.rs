@@ -0,0 +1,186 @@
+use anyhow::{Result, anyhow};
+use git2::Repository;
+use regex::Regex;
+
+/// Merge commits ("Merge pull request #123"), squash merges ending in "(#123)" and GitLab
+/// merge requests ("See merge request group/repo!123")
+const DEFAULT_PR_PATTERN: &str =
+    r"(?m)Merge pull request #(\d+)|\(#(\d+)\)$|See merge request \S*!(\d+)";
+
+/// JIRA-style issue keys like "PLAT-456", starting with at least two letters
+const DEFAULT_ISSUE_PATTERN: &str = r"\b[A-Z]{2}[A-Z0-9]*-\d+\b";
+
+/// Names of standards, encodings and algorithms that look like issue keys ("UTF-8",
+/// "SHA-256", "RFC-2119"), skipped by the default issue pattern only
+const NOT_ISSUE_KEYS: &[&str] = &[
+    "AES", "ANSI", "CP", "CRC", "CVE", "ECMA", "ES", "GPL", "HTTP", "IEC", "IEEE", "ISO", "LGPL",
+    "MD", "RFC", "RSA", "SHA", "TLS", "UTF", "WIN",
+];
+
+/// Pull requests and issues mentioned in a commit message
+#[derive(Debug, Default, PartialEq)]
+pub struct CommitRefs {
+    pub pull_requests: Vec<u64>,
+    pub issues: Vec<String>,
+}
+
+impl CommitRefs {
+    /// Pull requests as "#123"
+    pub fn pull_request_labels(&self) -> Vec<String> {
+        self.pull_requests
+            .iter()
+            .map(|number| format!("#{}", number))
+            .collect()
+    }
+
+    /// Pull requests and issues for display, e.g. "#123 PLAT-456"
+    pub fn label(&self) -> String {
+        let mut labels = self.pull_request_labels();
+        labels.extend(self.issues.iter().cloned());
+        labels.join(" ")
+    }
+}
+
+/// The regexes that find pull requests and issues in commit messages
+///
+/// They can be replaced with `wer.prPattern` and `wer.issuePattern` in git config, and an
+/// empty value turns a kind off. The first capture group that matched is the reference (for
+/// pull requests it must be the number), or the whole match when there are no groups.
+pub struct RefPatterns {
+    pull_request: Option<Regex>,
+    issue: Option<Regex>,
+    /// Issue key prefixes that are never issues
+    not_issue_keys: &'static [&'static str],
+}
+
+impl RefPatterns {
+    pub fn from_repo(repo: &Repository) -> Result<Self> {
+        let config = repo.config()?;
+        let pattern = |name: &str, default: &str| -> Result<Option<Regex>> {
+            let pattern = config
+                .get_string(name)
+                .unwrap_or_else(|_| default.to_string());
+            compile(name, &pattern)
+        };
+        let custom_issues = config.get_string("wer.issuePattern").is_ok();
+        Ok(Self {
+            pull_request: pattern("wer.prPattern", DEFAULT_PR_PATTERN)?,
+            issue: pattern("wer.issuePattern", DEFAULT_ISSUE_PATTERN)?,
+            not_issue_keys: if custom_issues { &[] } else { NOT_ISSUE_KEYS },
+        })
+    }
+
+    /// Pull requests and issues in a commit message, in order of appearance and without repeats
+    pub fn extract(&self, message: &str) -> CommitRefs {
+        let mut refs = CommitRefs::default();
+        for number in matches(self.pull_request.as_ref(), message) {
+            if let Ok(number) = number.trim_start_matches('#').parse()
+                && !refs.pull_requests.contains(&number)
+            {
+                refs.pull_requests.push(number);
+            }
+        }
+        for issue in matches(self.issue.as_ref(), message) {
+            let prefix = issue.split('-').next().unwrap_or("");
+            if !self.not_issue_keys.contains(&prefix) && !refs.issues.contains(&issue) {
+                refs.issues.push(issue);
+            }
+        }
+        refs
+    }
+}
+
+fn compile(name: &str, pattern: &str) -> Result<Option<Regex>> {
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+    Regex::new(pattern)
+        .map(Some)
+        .map_err(|e| anyhow!("Invalid regular expression in {}: {}", name, e))
+}
+
+/// The first capture group that took part in each match, or the whole match
+fn matches(regex: Option<&Regex>, text: &str) -> Vec<String> {
+    let Some(regex) = regex else {
+        return Vec::new();
+    };
+    regex
+        .captures_iter(text)
+        .filter_map(|captures| {
+            captures
+                .iter()
+                .skip(1)
+                .flatten()
+                .next()
+                .or_else(|| captures.get(0))
+                .map(|found| found.as_str().to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> RefPatterns {
+        RefPatterns {
+            pull_request: compile("pr", DEFAULT_PR_PATTERN).unwrap(),
+            issue: compile("issue", DEFAULT_ISSUE_PATTERN).unwrap(),
+            not_issue_keys: NOT_ISSUE_KEYS,
+        }
+    }
+
+    #[test]
+    fn test_default_patterns() {
+        let patterns = defaults();
+        assert_eq!(
+            patterns.extract("Merge pull request #123 from me/branch\n\nPLAT-456: fix login"),
+            CommitRefs {
+                pull_requests: vec![123],
+                issues: vec!["PLAT-456".to_string()],
+            }
+        );
+        assert_eq!(
+            patterns
+                .extract("Fix parser (#77)\n\nSee merge request group/repo!8")
+                .pull_requests,
+            vec![77, 8]
+        );
+        // "#5" in the middle of a line is not a squash merge, and keys repeat only once
+        let refs = patterns.extract("Handle #5 better for OPS-1 and OPS-1");
+        assert_eq!(refs.label(), "OPS-1");
+        assert_eq!(patterns.extract("Tidy up"), CommitRefs::default());
+    }
+
+    #[test]
+    fn test_standards_are_not_issues() {
+        let patterns = defaults();
+        let refs = patterns.extract(
+            "Read files as UTF-8, hash with SHA-256 per RFC-6234, dates in ISO-8601 (CVE-2024-1234)",
+        );
+        assert_eq!(refs, CommitRefs::default());
+        // A single letter before the dash is not a key either
+        assert!(patterns.extract("Use the A-1 layout").issues.is_empty());
+        assert_eq!(
+            patterns.extract("UTF-8 fix for AB-12").issues,
+            vec!["AB-12".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_configured_patterns() {
+        let patterns = RefPatterns {
+            pull_request: compile("pr", r"PR (\d+)").unwrap(),
+            issue: compile("issue", r"bug \d+").unwrap(),
+            not_issue_keys: &[],
+        };
+        let refs = patterns.extract("PR 9 fixes bug 12 (#3)");
+        assert_eq!(refs.pull_requests, vec![9]);
+        assert_eq!(refs.issues, vec!["bug 12"]);
+        assert_eq!(refs.label(), "#9 bug 12");
+
+        assert!(compile("wer.prPattern", "").unwrap().is_none());
+        let error = compile("wer.prPattern", "(").unwrap_err();
+        assert!(error.to_string().contains("wer.prPattern"));
+    }
+}
@@ -0,0 +1,312 @@
+use anyhow::{Result, anyhow};
+use git2::{Delta, DiffFindOptions, DiffOptions, Oid, Patch, Repository};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::copies::CopyDetection;
+use crate::git::{ColorScheme, blame_at, open_repo};
+use crate::utils::format_timestamp_day_month_year;
+
+/// Branches tried, in order, as the base when no range is given
+const DEFAULT_BASES: [&str; 5] = [
+    "origin/HEAD",
+    "origin/main",
+    "origin/master",
+    "main",
+    "master",
+];
+
+/// Someone who wrote lines touched by the change
+struct Candidate {
+    name: String,
+    email: String,
+    lines: usize,
+    files: HashSet<PathBuf>,
+    last_touched: i64,
+    score: f64,
+}
+
+/// Suggest reviewers for the changes between two revisions
+/// The lines a change modifies or deletes are blamed as they were before the change, and the
+/// people who wrote them are ranked by how much of it they own, weighted towards recent work
+pub fn suggest_reviewers(
+    git_dir: Option<&Path>,
+    range: Option<&str>,
+    max: usize,
+    no_color: bool,
+) -> Result<String> {
+    let repo = open_repo(git_dir)?;
+    let colors = ColorScheme::new(no_color);
+
+    let (base_name, head_name) = parse_range(&repo, range)?;
+    let base = resolve_commit(&repo, &base_name)?;
+    let head = resolve_commit(&repo, &head_name)?;
+    let merge_base = repo
+        .merge_base(base, head)
+        .map_err(|_| anyhow!("'{}' and '{}' have no common history", base_name, head_name))?;
+
+    let old_tree = repo.find_commit(merge_base)?.tree()?;
+    let head_commit = repo.find_commit(head)?;
+    let new_tree = head_commit.tree()?;
+
+    let mut diff_options = DiffOptions::new();
+    diff_options.context_lines(0);
+    let mut diff =
+        repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_options))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    if diff.deltas().len() == 0 {
+        return Err(anyhow!(
+            "No changes between '{}' and '{}'. Pass a range like HEAD~3..HEAD",
+            base_name,
+            head_name
+        ));
+    }
+
+    // The change's own authors shouldn't review it
+    let mut change_authors = HashSet::new();
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    revwalk.hide(merge_base)?;
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        change_authors.insert(author_key(commit.author().email().unwrap_or("")));
+    }
+
+    let now = head_commit.time().seconds();
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+    let mut touched_lines = 0;
+    let mut touched_files = 0;
+
+    for (delta_index, delta) in diff.deltas().enumerate() {
+        // New files have no previous owners
+        if delta.status() == Delta::Added {
+            continue;
+        }
+        let Some(old_path) = delta.old_file().path().map(Path::to_path_buf) else {
+            continue;
+        };
+        let Some(patch) = Patch::from_diff(&diff, delta_index)? else {
+            continue;
+        };
+
+        let lines = touched_old_lines(&patch)?;
+        if lines.is_empty() {
+            continue;
+        }
+        touched_files += 1;
+        touched_lines += lines.len();
+
+        let blame = blame_at(&repo, &old_path, Some(merge_base), CopyDetection::default())?;
+        for line in lines {
+            let Some(hunk) = blame.get_line(line) else {
+                continue;
+            };
+            let signature = hunk.final_signature();
+            let email = signature.email().unwrap_or("").to_string();
+            let key = author_key(&email);
+            if change_authors.contains(&key) {
+                continue;
+            }
+
+            let time = signature.when().seconds();
+            let candidate = candidates.entry(key).or_insert_with(|| Candidate {
+                name: signature.name().unwrap_or("Unknown").to_string(),
+                email,
+                lines: 0,
+                files: HashSet::new(),
+                last_touched: time,
+                score: 0.0,
+            });
+            candidate.lines += 1;
+            candidate.files.insert(old_path.clone());
+            candidate.last_touched = candidate.last_touched.max(time);
+            candidate.score += recency_weight(now, time);
+        }
+    }
+
+    let mut ranked: Vec<Candidate> = candidates.into_values().collect();
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.files.len().cmp(&a.files.len()))
+            .then(a.name.cmp(&b.name))
+    });
+
+    let mut result = format!(
+        "Suggested reviewers for {}..{} ({} in {}):",
+        short_id(merge_base),
+        short_id(head),
+        counted(touched_lines, "changed line"),
+        counted(touched_files, "file")
+    );
+
+    if ranked.is_empty() {
+        result.push_str("\nNo candidates: the touched lines were all written by the change's own authors or the files are new");
+        return Ok(result);
+    }
+
+    for (rank, candidate) in ranked.iter().take(max).enumerate() {
+        result.push_str(&format!(
+            "\n{:>3}. {}{}{} <{}> - {} in {}, last touched {}{}{}",
+            rank + 1,
+            colors.commit,
+            candidate.name,
+            colors.reset,
+            candidate.email,
+            counted(candidate.lines, "line"),
+            counted(candidate.files.len(), "file"),
+            colors.date,
+            format_timestamp_day_month_year(candidate.last_touched),
+            colors.reset
+        ));
+    }
+
+    Ok(result)
+}
+
+/// Split "<base>..<head>" (or "<base>...<head>", or just "<base>") into its revisions
+/// Without a range, the current branch is compared against the default branch
+fn parse_range(repo: &Repository, range: Option<&str>) -> Result<(String, String)> {
+    let Some(range) = range else {
+        let base = DEFAULT_BASES
+            .iter()
+            .find(|name| repo.revparse_single(name).is_ok())
+            .ok_or_else(|| {
+                anyhow!("Cannot find a default branch to compare against, pass <base>..<head>")
+            })?;
+        return Ok((base.to_string(), "HEAD".to_string()));
+    };
+
+    let (base, head) = match range.split_once("...").or_else(|| range.split_once("..")) {
+        Some((base, head)) => (base, head),
+        None => (range, "HEAD"),
+    };
+    let or_head = |rev: &str| if rev.is_empty() { "HEAD" } else { rev }.to_string();
+
+    Ok((or_head(base), or_head(head)))
+}
+
+fn resolve_commit(repo: &Repository, rev: &str) -> Result<Oid> {
+    Ok(repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| anyhow!("Cannot resolve revision '{}' to a commit", rev))?
+        .id())
+}
+
+/// Lines of the old file a patch modifies or deletes
+/// Pure insertions count the line they were inserted after, whose author knows the surroundings
+fn touched_old_lines(patch: &Patch) -> Result<Vec<usize>> {
+    let mut lines = Vec::new();
+    for hunk_index in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(hunk_index)?;
+        let (start, count) = (hunk.old_start() as usize, hunk.old_lines() as usize);
+        if count == 0 {
+            lines.push(start.max(1));
+        } else {
+            lines.extend(start..start + count);
+        }
+    }
+    lines.dedup();
+    Ok(lines)
+}
+
+/// Weight of a line written at `time`: a line from today counts 1, a year old line counts 1/2
+fn recency_weight(now: i64, time: i64) -> f64 {
+    let age_years = (now - time).max(0) as f64 / (365.0 * 24.0 * 60.0 * 60.0);
+    1.0 / (1.0 + age_years)
+}
+
+/// People are matched by email, case-insensitively
+fn author_key(email: &str) -> String {
+    email.to_lowercase()
+}
+
+fn short_id(oid: Oid) -> String {
+    oid.to_string().chars().take(7).collect()
+}
+
+/// "1 line", "2 lines"
+fn counted(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_range() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        let parse = |range| parse_range(&repo, Some(range)).unwrap();
+
+        assert_eq!(parse("main..topic"), ("main".into(), "topic".into()));
+        assert_eq!(parse("main...topic"), ("main".into(), "topic".into()));
+        assert_eq!(parse("main.."), ("main".into(), "HEAD".into()));
+        assert_eq!(parse("v1.0"), ("v1.0".into(), "HEAD".into()));
+    }
+
+    #[test]
+    fn test_reviewers_ranked_by_owned_lines_excluding_change_authors() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("lib.rs", "1\n2\n3\n4\n5\n6\n"), ("other.rs", "x\n")],
+            "Alice",
+            1749456964,
+            "add lib",
+        );
+        commit_files(
+            &repo,
+            &[("lib.rs", "1\n2\n3\nfour\nfive\nsix\n")],
+            "Bob",
+            1749556964,
+            "rework end",
+        );
+        commit_files(
+            &repo,
+            &[("lib.rs", "1\n2\nthree\nfour\nfive\nsix\n")],
+            "Carol",
+            1749606964,
+            "tweak three",
+        );
+        // The change under review, by Carol: touches lines owned by Alice, Bob and herself
+        commit_files(
+            &repo,
+            &[
+                ("lib.rs", "one\n2\nTHREE\nFOUR\nFIVE\nsix\n"),
+                ("new.rs", "new\n"),
+            ],
+            "Carol",
+            1749656964,
+            "review me",
+        );
+
+        let output = suggest_reviewers(Some(dir.path()), Some("HEAD~1..HEAD"), 5, true).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(
+            lines[0].ends_with("(4 changed lines in 1 file):"),
+            "{}",
+            output
+        );
+        assert_eq!(
+            lines[1],
+            "  1. Bob <bob@example.com> - 2 lines in 1 file, last touched 10 Jun 2025"
+        );
+        assert_eq!(
+            lines[2],
+            "  2. Alice <alice@example.com> - 1 line in 1 file, last touched 09 Jun 2025"
+        );
+        assert_eq!(lines.len(), 3, "{}", output);
+    }
+}
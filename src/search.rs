@@ -1,11 +1,19 @@
 use anyhow::{Result, anyhow};
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::git::normalize_tree_path;
+use crate::utils::expand_path;
+
+/// How many commits the search for deleted paths by name looks through, so that a typo
+/// doesn't diff the whole history of a large repository before failing
+const DELETED_SEARCH_DEPTH: usize = 1000;
+
 /// Search for a file or directory by name starting from current directory
 /// Handles different path types:
-/// - ~/path: home directory paths (returned as-is)
-/// - /absolute/path: absolute paths (returned as-is)
+/// - ~/path, ~user/path, $VAR/path: expanded, then treated like any other path
+/// - /absolute/path: absolute paths (canonicalized when they exist)
 /// - ../relative/path or ./path or subdir/path: relative paths (checked directly)
 /// - filename: bare filename (searched recursively in current directory)
 pub fn find_all_matches(input: &str) -> Result<Vec<String>> {
@@ -14,24 +22,32 @@ pub fn find_all_matches(input: &str) -> Result<Vec<String>> {
 
 /// Internal function that can optionally specify a base directory (for testing)
 fn find_all_matches_from(input: &str, base_dir: Option<&Path>) -> Result<Vec<String>> {
-    // If it's already an absolute path or starts with ~/, return as-is
-    if input.starts_with('/') || input.starts_with("~/") {
-        return Ok(vec![input.to_string()]);
-    }
-
     let current_dir = match base_dir {
         Some(dir) => dir.to_path_buf(),
         None => std::env::current_dir()?,
     };
 
-    // If it contains path separators, treat it as a relative path and check if it exists
+    // Expand ~ and $VAR, unless the input names an existing path literally (e.g. "price$list.txt")
+    let expanded = if current_dir.join(input).exists() {
+        input.to_string()
+    } else {
+        expand_path(input)?
+    };
+    let input = expanded.as_str();
+
+    // If it's an absolute path, use it directly (resolving symlinks when it exists)
+    if Path::new(input).is_absolute() {
+        return Ok(vec![
+            fs::canonicalize(input)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| input.to_string()),
+        ]);
+    }
+
+    // If it contains path separators, treat it as a relative path
+    // Paths missing on disk are kept too, they may have been deleted and get looked up in history
     if input.contains('/') {
-        let path = current_dir.join(input);
-        if path.exists() {
-            return Ok(vec![input.to_string()]);
-        } else {
-            return Err(anyhow!("Path '{}' not found", input));
-        }
+        return Ok(vec![input.to_string()]);
     }
 
     // If it exists as a file/directory in current directory, use it
@@ -45,6 +61,22 @@ fn find_all_matches_from(input: &str, base_dir: Option<&Path>) -> Result<Vec<Str
     search_recursive(&current_dir, input, &mut matches)?;
 
     if matches.is_empty() {
+        // Not on disk, but it may have been deleted: search the repository's history by name
+        let (deleted, truncated) =
+            find_deleted_matches_from(&current_dir, input).unwrap_or_default();
+        if !deleted.is_empty() {
+            return Ok(deleted);
+        }
+
+        if truncated {
+            // A full path is looked up in all of history, which is cheaper than a search by name
+            return Err(anyhow!(
+                "No file or directory named '{}' found starting from current directory, or deleted in the last {} commits. Give its full path (e.g. 'dir/{}') to look further back.",
+                input,
+                DELETED_SEARCH_DEPTH,
+                input
+            ));
+        }
         return Err(anyhow!(
             "No file or directory named '{}' found starting from current directory",
             input
@@ -63,6 +95,124 @@ fn find_all_matches_from(input: &str, base_dir: Option<&Path>) -> Result<Vec<Str
         .collect())
 }
 
+/// Search the recent history of the repository containing `current_dir` for deleted paths by
+/// name, returning paths below `current_dir` relative to it like the filesystem search does,
+/// and whether history goes back further than was searched
+fn find_deleted_matches_from(current_dir: &Path, name: &str) -> Result<(Vec<String>, bool)> {
+    let repo = Repository::discover(current_dir)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?
+        .canonicalize()?;
+    let current_dir = current_dir.canonicalize()?;
+
+    let (deleted, truncated) = find_deleted_matches(&repo, name, DELETED_SEARCH_DEPTH)?;
+    let deleted = deleted
+        .into_iter()
+        .filter_map(|path| {
+            workdir
+                .join(path)
+                .strip_prefix(&current_dir)
+                .ok()
+                .map(|path| path.to_string_lossy().to_string())
+        })
+        .collect();
+    Ok((deleted, truncated))
+}
+
+/// Search the last `depth` commits for files or directories with the given name that no longer
+/// exist at HEAD
+/// Returns repository-relative paths, most recently deleted first, and whether history goes
+/// back further than `depth`
+fn find_deleted_matches(
+    repo: &Repository,
+    name: &str,
+    depth: usize,
+) -> Result<(Vec<PathBuf>, bool)> {
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+
+    let mut matches: Vec<PathBuf> = Vec::new();
+    for (index, commit_id) in revwalk.enumerate() {
+        if index == depth {
+            return Ok((matches, true));
+        }
+        let commit = repo.find_commit(commit_id?)?;
+        let Ok(parent) = commit.parent(0) else {
+            continue;
+        };
+
+        let diff = repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)?;
+        for delta in diff.deltas() {
+            if delta.status() != git2::Delta::Deleted {
+                continue;
+            }
+            let Some(deleted_path) = delta.old_file().path() else {
+                continue;
+            };
+
+            // The deleted file itself, or one of its (possibly also deleted) parent directories
+            for candidate in deleted_path.ancestors() {
+                if candidate
+                    .file_name()
+                    .is_some_and(|file_name| file_name == name)
+                    && head_tree.get_path(candidate).is_err()
+                    && !matches.iter().any(|found| found == candidate)
+                {
+                    matches.push(candidate.to_path_buf());
+                }
+            }
+        }
+    }
+
+    Ok((matches, false))
+}
+
+/// Search a repository tree at a revision (HEAD when unset) for a path or a bare name
+/// Works without a working copy, so it can be used on bare repositories
+pub fn find_tree_matches(repo: &Repository, rev: Option<&str>, input: &str) -> Result<Vec<String>> {
+    let rev_name = rev.unwrap_or("HEAD");
+    let tree = repo
+        .revparse_single(rev_name)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|_| anyhow!("Cannot resolve revision '{}'", rev_name))?;
+
+    // Exact paths (and the root) are used directly
+    let normalized = normalize_tree_path(input);
+    if normalized.as_os_str().is_empty() || tree.get_path(&normalized).is_ok() {
+        return Ok(vec![input.to_string()]);
+    }
+
+    if input.contains('/') {
+        return Err(anyhow!("Path '{}' not found at '{}'", input, rev_name));
+    }
+
+    // Otherwise, it's just a name - search the tree for it
+    let mut matches = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |parent, entry| {
+        let name = entry.name().unwrap_or("");
+        if name == input {
+            matches.push(format!("{}{}", parent, name));
+        }
+        if entry.kind() == Some(ObjectType::Tree) && is_ignored_directory(name) {
+            TreeWalkResult::Skip
+        } else {
+            TreeWalkResult::Ok
+        }
+    })?;
+
+    if matches.is_empty() {
+        return Err(anyhow!(
+            "No file or directory named '{}' found at '{}'",
+            input,
+            rev_name
+        ));
+    }
+
+    Ok(matches)
+}
+
 /// Recursively search for all files or directories by name and collect them
 fn search_recursive(dir: &Path, target_name: &str, matches: &mut Vec<PathBuf>) -> Result<()> {
     let entries = fs::read_dir(dir)?;
@@ -106,6 +256,7 @@ fn is_ignored_directory(name: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{commit_files, remove_files};
     use std::fs::{self, File};
     use std::io::Write;
     use tempfile::tempdir;
@@ -120,10 +271,56 @@ mod tests {
 
     #[test]
     fn test_find_all_matches_home_tilde() {
-        // function should recognize a home path and return it directly.
-        let input = "~/myfile.txt";
-        let result = find_all_matches(input).unwrap();
-        assert_eq!(result, vec![input.to_string()]);
+        // function should expand a home path and return it directly.
+        let home = std::env::var("HOME").unwrap();
+        let result = find_all_matches("~/wer-nonexistent-file.txt").unwrap();
+        assert_eq!(
+            result,
+            vec![format!(
+                "{}/wer-nonexistent-file.txt",
+                home.trim_end_matches('/')
+            )]
+        );
+    }
+
+    #[test]
+    fn test_find_all_matches_literal_names_are_not_expanded() {
+        let dir = tempdir().unwrap();
+        // HOME is set, but the file is literally named like this
+        for name in ["a$HOME.txt", "$HOME", " spaced.txt ", "'quoted'"] {
+            File::create(dir.path().join(name)).unwrap();
+            let result = find_all_matches_from(name, Some(dir.path())).unwrap();
+            assert_eq!(result, vec![name.to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_find_all_matches_canonicalizes_absolute_path() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("testfile.txt");
+        File::create(&file_path).unwrap();
+
+        let input = format!("{}/./testfile.txt", dir.path().display());
+        let result = find_all_matches(&input).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                file_path
+                    .canonicalize()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_all_matches_literal_dollar_path() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join("price$list.txt")).unwrap();
+
+        let result = find_all_matches_from("price$list.txt", Some(dir.path())).unwrap();
+        assert_eq!(result, vec!["price$list.txt".to_string()]);
     }
 
     #[test]
@@ -189,6 +386,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_find_tree_matches_in_bare_repo() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("src/main.rs", "fn main() {}"), ("docs/main.rs", "// docs")],
+            "Alice",
+            1749456964,
+            "init",
+        );
+
+        let mut matches = find_tree_matches(&repo, None, "main.rs").unwrap();
+        matches.sort();
+        assert_eq!(matches, vec!["docs/main.rs", "src/main.rs"]);
+
+        let exact = find_tree_matches(&repo, Some("HEAD"), "src/main.rs").unwrap();
+        assert_eq!(exact, vec!["src/main.rs"]);
+
+        assert!(find_tree_matches(&repo, None, "src/missing.rs").is_err());
+        assert!(find_tree_matches(&repo, None, "missing.rs").is_err());
+    }
+
+    #[test]
+    fn test_find_all_matches_deleted_file_by_name() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[
+                ("src/old_module.rs", "// old"),
+                ("src/main.rs", "fn main() {}"),
+            ],
+            "Alice",
+            1749456964,
+            "init",
+        );
+        remove_files(
+            &repo,
+            &["src/old_module.rs"],
+            "Bob",
+            1749556964,
+            "remove old module",
+        );
+
+        let matches = find_all_matches_from("old_module.rs", Some(dir.path())).unwrap();
+        assert_eq!(matches, vec!["src/old_module.rs".to_string()]);
+
+        // Files that still exist are found on disk, not in history
+        let (deleted, truncated) = find_deleted_matches(&repo, "main.rs", 10).unwrap();
+        assert!(deleted.is_empty());
+        assert!(!truncated);
+
+        // The search stops after the given number of commits and says so
+        let (deleted, truncated) = find_deleted_matches(&repo, "old_module.rs", 1).unwrap();
+        assert_eq!(deleted, vec![PathBuf::from("src/old_module.rs")]);
+        assert!(truncated);
+        let (deleted, truncated) = find_deleted_matches(&repo, "typo.rs", 1).unwrap();
+        assert!(deleted.is_empty());
+        assert!(truncated);
+    }
+
     #[test]
     fn test_is_ignored_directory() {
         assert!(is_ignored_directory(".git"));
@@ -0,0 +1,475 @@
+use anyhow::{Result, anyhow};
+use git2::{DiffOptions, ObjectType, Oid, Repository};
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::copies::CopyDetection;
+use crate::git::{Target, TargetPath, first_parent_diff, open_repo};
+use crate::html::{escape_html, get_blame_html};
+use crate::porcelain::line_origins;
+use crate::refs::RefPatterns;
+use crate::syntax::SyntaxHighlighter;
+use crate::utils::{format_timestamp_day_month_year, percent_decode, percent_encode_path};
+
+/// A file or directory in a tree listing
+#[derive(Serialize)]
+struct TreeEntry {
+    name: String,
+    path: String,
+    /// "tree" for directories, "blob" for files and "commit" for submodules
+    kind: &'static str,
+    last_commit: Option<CommitSummary>,
+}
+
+#[derive(Serialize)]
+struct CommitSummary {
+    hash: String,
+    author: String,
+    email: String,
+    /// YYYY-MM-DD
+    date: String,
+    summary: String,
+    /// Pull request numbers and issue keys mentioned in the message
+    pull_requests: Vec<u64>,
+    issues: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BlameLine {
+    line: usize,
+    commit: String,
+    author: String,
+    date: String,
+    summary: String,
+    pull_requests: Vec<u64>,
+    issues: Vec<String>,
+    code: String,
+}
+
+/// A response: status code, content type and body
+type Response = (u16, &'static str, String);
+
+/// Serve the repository's tree at HEAD over HTTP until interrupted
+/// Routes: /tree/PATH and /blame/PATH render HTML, /api/tree/PATH and /api/blame/PATH JSON
+pub fn serve(git_dir: Option<&Path>, host: &str, port: u16) -> Result<()> {
+    let git_dir = open_repo(git_dir)?.path().to_path_buf();
+    let server = tiny_http::Server::http((host, port))
+        .map_err(|e| anyhow!("Cannot listen on {}:{}: {}", host, port, e))?;
+    let address = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| anyhow!("Not listening on an IP address"))?;
+    println!("Serving {} at http://{}/", git_dir.display(), address);
+
+    for request in server.incoming_requests() {
+        let (status, content_type, body) = if *request.method() == tiny_http::Method::Get {
+            respond(&git_dir, request.url())
+        } else {
+            (
+                405,
+                "text/plain; charset=utf-8",
+                "Method not allowed".to_string(),
+            )
+        };
+        let header = tiny_http::Header::from_bytes("Content-Type", content_type)
+            .map_err(|_| anyhow!("Invalid content type: {}", content_type))?;
+        let response = tiny_http::Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("Error: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Route a request URL to a page or API response
+fn respond(git_dir: &Path, url: &str) -> Response {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let route = |prefix: &str| {
+        path.strip_prefix(prefix)
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+            .map(|rest| percent_decode(rest.trim_matches('/')))
+    };
+
+    let result = if path == "/" {
+        tree_page(git_dir, "")
+    } else if let Some(tree) = route("/tree") {
+        tree.and_then(|tree| tree_page(git_dir, &tree))
+    } else if let Some(file) = route("/blame") {
+        file.and_then(|file| {
+            let target = tree_path(git_dir, &file);
+            get_blame_html(&target, None).map(|page| (200, "text/html; charset=utf-8", page))
+        })
+    } else if let Some(tree) = route("/api/tree") {
+        tree.and_then(|tree| {
+            let entries = tree_entries(git_dir, &tree)?;
+            Ok((
+                200,
+                "application/json",
+                serde_json::to_string_pretty(&entries)?,
+            ))
+        })
+    } else if let Some(file) = route("/api/blame") {
+        file.and_then(|file| {
+            let lines = blame_lines(git_dir, &file)?;
+            Ok((
+                200,
+                "application/json",
+                serde_json::to_string_pretty(&lines)?,
+            ))
+        })
+    } else {
+        Err(anyhow!("No such page: {}", path))
+    };
+
+    result.unwrap_or_else(|e| {
+        if path.starts_with("/api/") {
+            let error = serde_json::json!({ "error": e.to_string() });
+            (404, "application/json", error.to_string())
+        } else {
+            (
+                404,
+                "text/plain; charset=utf-8",
+                format!("Not found: {}", e),
+            )
+        }
+    })
+}
+
+fn tree_path(git_dir: &Path, path: &str) -> TargetPath {
+    TargetPath::Tree {
+        git_dir: git_dir.to_path_buf(),
+        rev: None,
+        path: path.to_string(),
+    }
+}
+
+/// Entries of a directory at HEAD, directories first, each with the last commit that touched it
+fn tree_entries(git_dir: &Path, directory: &str) -> Result<Vec<TreeEntry>> {
+    let repo = Repository::open(git_dir)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let tree = if directory.is_empty() {
+        head.tree()?
+    } else {
+        let entry = head
+            .tree()?
+            .get_path(Path::new(directory))
+            .map_err(|_| anyhow!("'{}' does not exist at HEAD", directory))?;
+        if entry.kind() != Some(ObjectType::Tree) {
+            return Err(anyhow!("'{}' is not a directory", directory));
+        }
+        entry.to_object(&repo)?.peel_to_tree()?
+    };
+
+    let names: HashSet<String> = tree
+        .iter()
+        .filter_map(|entry| entry.name().map(str::to_string))
+        .collect();
+    let last_commits = entry_last_commits(&repo, head.id(), Path::new(directory), &names)?;
+    let patterns = RefPatterns::from_repo(&repo)?;
+
+    let mut entries = Vec::new();
+    for entry in tree.iter() {
+        let name = entry.name().unwrap_or("").to_string();
+        let kind = match entry.kind() {
+            Some(ObjectType::Tree) => "tree",
+            Some(ObjectType::Commit) => "commit",
+            _ => "blob",
+        };
+        let last_commit = match last_commits.get(&name) {
+            Some(&commit_id) => Some(commit_summary(&repo, &patterns, commit_id)?),
+            None => None,
+        };
+        entries.push(TreeEntry {
+            path: Path::new(directory)
+                .join(&name)
+                .to_string_lossy()
+                .to_string(),
+            name,
+            kind,
+            last_commit,
+        });
+    }
+    entries.sort_by(|a, b| {
+        (a.kind != "tree")
+            .cmp(&(b.kind != "tree"))
+            .then(a.name.cmp(&b.name))
+    });
+
+    Ok(entries)
+}
+
+/// The newest commit touching each of `names` in `directory`, found in a single history walk
+fn entry_last_commits(
+    repo: &Repository,
+    start: Oid,
+    directory: &Path,
+    names: &HashSet<String>,
+) -> Result<HashMap<String, Oid>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(start)?;
+
+    let mut found = HashMap::new();
+    for commit_id in revwalk {
+        if found.len() == names.len() {
+            break;
+        }
+        let commit = repo.find_commit(commit_id?)?;
+        let mut options = DiffOptions::new();
+        if !directory.as_os_str().is_empty() {
+            options.pathspec(directory);
+        }
+        let diff = first_parent_diff(repo, &commit, Some(&mut options))?;
+
+        for delta in diff.deltas() {
+            for file in [delta.new_file().path(), delta.old_file().path()]
+                .into_iter()
+                .flatten()
+            {
+                let Some(name) = file
+                    .strip_prefix(directory)
+                    .ok()
+                    .and_then(|rest| rest.iter().next())
+                    .and_then(|name| name.to_str())
+                else {
+                    continue;
+                };
+                if names.contains(name) && !found.contains_key(name) {
+                    found.insert(name.to_string(), commit.id());
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+fn commit_summary(
+    repo: &Repository,
+    patterns: &RefPatterns,
+    commit_id: Oid,
+) -> Result<CommitSummary> {
+    let commit = repo.find_commit(commit_id)?;
+    let author = commit.author();
+    let refs = patterns.extract(commit.message().unwrap_or(""));
+    Ok(CommitSummary {
+        hash: commit_id.to_string(),
+        author: author.name().unwrap_or("Unknown").to_string(),
+        email: author.email().unwrap_or("").to_string(),
+        date: chrono::DateTime::from_timestamp(commit.time().seconds(), 0)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+        summary: commit.summary().unwrap_or("").to_string(),
+        pull_requests: refs.pull_requests,
+        issues: refs.issues,
+    })
+}
+
+fn blame_lines(git_dir: &Path, file: &str) -> Result<Vec<BlameLine>> {
+    let target = Target::open(&tree_path(git_dir, file), true)?;
+    let content = target.read_content()?;
+    let origins = line_origins(
+        &target,
+        &content,
+        1,
+        content.lines().count(),
+        CopyDetection::default(),
+    )?;
+    let patterns = RefPatterns::from_repo(&target.repo)?;
+
+    let mut commits = HashMap::new();
+    let mut lines = Vec::new();
+    for (index, (origin, code)) in origins.iter().zip(content.lines()).enumerate() {
+        let commit = match commits.entry(origin.commit_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(commit_summary(&target.repo, &patterns, origin.commit_id)?)
+            }
+        };
+        lines.push(BlameLine {
+            line: index + 1,
+            commit: commit.hash.clone(),
+            author: commit.author.clone(),
+            date: commit.date.clone(),
+            summary: commit.summary.clone(),
+            pull_requests: commit.pull_requests.clone(),
+            issues: commit.issues.clone(),
+            code: code.to_string(),
+        });
+    }
+
+    Ok(lines)
+}
+
+fn tree_page(git_dir: &Path, directory: &str) -> Result<Response> {
+    let entries = tree_entries(git_dir, directory)?;
+    let (background, foreground) = SyntaxHighlighter::new().theme_colors();
+    let repo_name = repository_name(git_dir);
+
+    // Breadcrumbs: the repository, then every directory on the way
+    let mut crumbs = format!("<a href=\"/\">{}</a>", escape_html(&repo_name));
+    let mut prefix = PathBuf::new();
+    for part in Path::new(directory).iter() {
+        prefix.push(part);
+        crumbs.push_str(&format!(
+            " / <a href=\"/tree/{}\">{}</a>",
+            percent_encode_path(&prefix.to_string_lossy()),
+            escape_html(&part.to_string_lossy())
+        ));
+    }
+
+    let mut rows = String::new();
+    for entry in &entries {
+        let (link, suffix) = match entry.kind {
+            "tree" => (format!("/tree/{}", percent_encode_path(&entry.path)), "/"),
+            "blob" => (format!("/blame/{}", percent_encode_path(&entry.path)), ""),
+            _ => (String::new(), " (submodule)"),
+        };
+        let name = if link.is_empty() {
+            format!("{}{}", escape_html(&entry.name), suffix)
+        } else {
+            format!(
+                "<a href=\"{}\">{}{}</a>",
+                link,
+                escape_html(&entry.name),
+                suffix
+            )
+        };
+        let commit = match &entry.last_commit {
+            Some(commit) => format!(
+                "<td class=\"commit\" title=\"{}\">{}</td><td>{}</td><td>{}</td><td class=\"date\">{}</td>",
+                commit.hash,
+                &commit.hash[..7],
+                escape_html(&commit.summary),
+                escape_html(&commit.author),
+                format_date(&commit.date)
+            ),
+            None => "<td></td><td></td><td></td><td></td>".to_string(),
+        };
+        rows.push_str(&format!("<tr><td>{}</td>{}</tr>\n", name, commit));
+    }
+
+    let title = escape_html(&format!("{}/{}", repo_name, directory));
+    let api = format!("/api/tree/{}", percent_encode_path(directory));
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ margin: 0; font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; background: {background}; color: {foreground}; }}
+h1 {{ font-size: 1rem; font-weight: 600; margin: 0; padding: 12px 16px; border-bottom: 1px solid rgba(255, 255, 255, 0.15); }}
+a {{ color: inherit; }}
+table {{ border-collapse: collapse; width: 100%; font-size: 14px; }}
+td {{ padding: 4px 16px; border-bottom: 1px solid rgba(255, 255, 255, 0.08); white-space: nowrap; }}
+td.commit {{ font-family: ui-monospace, Menlo, Consolas, monospace; opacity: 0.7; }}
+td.date {{ text-align: right; opacity: 0.7; }}
+footer {{ padding: 12px 16px; font-size: 12px; opacity: 0.6; }}
+</style>
+</head>
+<body>
+<h1>{crumbs}</h1>
+<table>
+{rows}</table>
+<footer><a href="{api}">JSON</a></footer>
+</body>
+</html>
+"#
+    );
+
+    Ok((200, "text/html; charset=utf-8", page))
+}
+
+/// "2025-06-09" as "09 Jun 2025", like the rest of wer's output
+fn format_date(date: &str) -> String {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| format_timestamp_day_month_year(date.and_utc().timestamp()))
+        .unwrap_or_else(|| date.to_string())
+}
+
+/// Name shown for the repository: its directory, without ".git"
+fn repository_name(git_dir: &Path) -> String {
+    let path = match git_dir.file_name().and_then(|name| name.to_str()) {
+        Some(".git") => git_dir.parent().unwrap_or(git_dir),
+        _ => git_dir,
+    };
+    path.file_name()
+        .map(|name| name.to_string_lossy().trim_end_matches(".git").to_string())
+        .unwrap_or_else(|| "repository".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_routes() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path().join("demo.git")).unwrap();
+        commit_files(
+            &repo,
+            &[("src/main.rs", "fn main() {}\n"), ("my notes.md", "hi\n")],
+            "Alice",
+            1749456964,
+            "init",
+        );
+        commit_files(
+            &repo,
+            &[("src/lib.rs", "pub fn lib() {}\n")],
+            "Bob",
+            1749556964,
+            "add <lib> (#12)",
+        );
+        let git_dir = repo.path();
+
+        let (status, content_type, page) = respond(git_dir, "/");
+        assert_eq!((status, content_type), (200, "text/html; charset=utf-8"));
+        assert!(page.contains("<a href=\"/\">demo</a>"));
+        // Directories first, with the newest commit inside them
+        let src = page.find("<a href=\"/tree/src\">src/</a>").unwrap();
+        let notes = page
+            .find("<a href=\"/blame/my%20notes.md\">my notes.md</a>")
+            .unwrap();
+        assert!(src < notes);
+        assert!(page.contains(
+            "<td>add &lt;lib&gt; (#12)</td><td>Bob</td><td class=\"date\">10 Jun 2025</td>"
+        ));
+
+        let (status, _, json) = respond(git_dir, "/api/tree/src");
+        assert_eq!(status, 200);
+        let entries: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(entries[0]["path"], "src/lib.rs");
+        assert_eq!(entries[0]["last_commit"]["author"], "Bob");
+        assert_eq!(
+            entries[0]["last_commit"]["pull_requests"],
+            serde_json::json!([12])
+        );
+        assert_eq!(entries[1]["name"], "main.rs");
+        assert_eq!(entries[1]["last_commit"]["date"], "2025-06-09");
+
+        let (status, _, json) = respond(git_dir, "/api/blame/src/main.rs?x=1");
+        assert_eq!(status, 200);
+        let lines: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(lines[0]["line"], 1);
+        assert_eq!(lines[0]["author"], "Alice");
+        assert_eq!(lines[0]["code"], "fn main() {}");
+
+        let (status, _, page) = respond(git_dir, "/blame/my%20notes.md");
+        assert_eq!(status, 200);
+        assert!(page.contains("Blame: my notes.md"));
+
+        let (status, content_type, json) = respond(git_dir, "/api/blame/missing.rs");
+        assert_eq!((status, content_type), (404, "application/json"));
+        assert!(json.contains("\"error\""));
+        assert_eq!(respond(git_dir, "/treehouse").0, 404);
+    }
+}
@@ -1,9 +1,12 @@
 use anyhow::Result;
 use std::path::Path;
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{Style, ThemeSet};
+use syntect::highlighting::{Color, Style, ThemeSet};
+use syntect::html::{IncludeBackground, styled_line_to_highlighted_html};
 use syntect::parsing::SyntaxSet;
-use syntect::util::as_24_bit_terminal_escaped;
+use syntect::util::{LinesWithEndings, as_24_bit_terminal_escaped};
+
+const THEME: &str = "base16-eighties.dark";
 
 pub struct SyntaxHighlighter {
     syntax_set: SyntaxSet,
@@ -22,7 +25,7 @@ impl SyntaxHighlighter {
         // Get syntax definition from file extension with fallbacks for TypeScript
         let syntax = self.get_syntax_for_file(file_path);
 
-        let theme = &self.theme_set.themes["base16-eighties.dark"];
+        let theme = &self.theme_set.themes[THEME];
 
         let mut highlighter = HighlightLines::new(syntax, theme);
 
@@ -33,6 +36,36 @@ impl SyntaxHighlighter {
         Ok(as_24_bit_terminal_escaped(&ranges[..], false))
     }
 
+    /// Highlight a whole file as HTML with inline styles, one string per line
+    /// Unlike highlight_line, parser state carries over between lines (e.g. block comments)
+    pub fn highlight_html(&self, content: &str, file_path: &Path) -> Result<Vec<String>> {
+        let syntax = self.get_syntax_for_file(file_path);
+        let mut highlighter = HighlightLines::new(syntax, &self.theme_set.themes[THEME]);
+
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(content) {
+            let ranges = highlighter.highlight_line(line, &self.syntax_set)?;
+            let html = styled_line_to_highlighted_html(&ranges[..], IncludeBackground::No)?;
+            lines.push(html.trim_end_matches(['\r', '\n']).to_string());
+        }
+
+        Ok(lines)
+    }
+
+    /// Background and foreground colors of the theme, as CSS colors
+    pub fn theme_colors(&self) -> (String, String) {
+        let settings = &self.theme_set.themes[THEME].settings;
+        let css = |color: Option<Color>, fallback: &str| match color {
+            Some(Color { r, g, b, .. }) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            None => fallback.to_string(),
+        };
+
+        (
+            css(settings.background, "#2d2d2d"),
+            css(settings.foreground, "#d3d0c8"),
+        )
+    }
+
     fn get_syntax_for_file(&self, file_path: &Path) -> &syntect::parsing::SyntaxReference {
         // First try the file extension
         if let Ok(Some(syntax)) = self.syntax_set.find_syntax_for_file(file_path) {
@@ -0,0 +1,577 @@
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::cli::Format;
+use crate::copies::CopyDetection;
+use crate::git::{Target, TargetPath, blame_at, last_commits, working_tree_status};
+use crate::refs::RefPatterns;
+use crate::utils::shown_lines;
+
+/// Separates several pull requests or issues in one cell
+const REF_SEPARATOR: &str = ", ";
+
+/// A table cell; CSV, TSV and Markdown show every cell as text, JSON keeps numbers and lists
+#[derive(Debug, PartialEq)]
+pub enum Cell {
+    Text(String),
+    /// A line number, missing for lines blame knows nothing about
+    Line(Option<usize>),
+    PullRequests(Vec<u64>),
+    Issues(Vec<String>),
+}
+
+impl Cell {
+    pub fn text(&self) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Line(line) => line.map(|line| line.to_string()).unwrap_or_default(),
+            Cell::PullRequests(numbers) => numbers
+                .iter()
+                .map(|number| format!("#{}", number))
+                .collect::<Vec<_>>()
+                .join(REF_SEPARATOR),
+            Cell::Issues(issues) => issues.join(REF_SEPARATOR),
+        }
+    }
+
+    fn json(&self) -> Value {
+        match self {
+            Cell::Text(text) => Value::from(text.as_str()),
+            Cell::Line(line) => line.map_or(Value::Null, Value::from),
+            Cell::PullRequests(numbers) => Value::from(numbers.clone()),
+            Cell::Issues(issues) => Value::from(issues.clone()),
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl PartialEq<&str> for Cell {
+    fn eq(&self, other: &&str) -> bool {
+        self.text() == *other
+    }
+}
+
+/// Column names for normal mode (and --last) rows
+/// "deleted_in" names the commit (the date with `date_only`) that deleted the path, and "status"
+/// its uncommitted changes, or "submodule" when the rows are the superproject's bumps
+pub fn commit_headers(date_only: bool) -> Vec<&'static str> {
+    if date_only {
+        vec!["date", "deleted_in", "status"]
+    } else {
+        vec![
+            "commit",
+            "author",
+            "email",
+            "date",
+            "message",
+            "pull_requests",
+            "issues",
+            "deleted_in",
+            "status",
+        ]
+    }
+}
+
+/// Column names for blame rows, with where each line came from when `copies` is enabled
+pub fn blame_headers(date_only: bool, copies: CopyDetection) -> Vec<&'static str> {
+    if date_only {
+        return vec!["date", "line", "code"];
+    }
+    let mut headers = vec![
+        "commit",
+        "author",
+        "date",
+        "line",
+        "summary",
+        "pull_requests",
+        "issues",
+    ];
+    if copies.is_enabled() {
+        headers.extend(["orig_path", "orig_line"]);
+    }
+    headers.push("code");
+    headers
+}
+
+/// The last commit touching a path, or with `last` one commit per recent contributor,
+/// as table rows with the full commit message
+/// Like text mode, deleted paths report the commits before the deletion, submodules the
+/// superproject commits that bumped them, and untracked paths a row with only their status
+pub fn last_commit_rows(
+    path: &TargetPath,
+    date_only: bool,
+    last: Option<usize>,
+) -> Result<Vec<Vec<Cell>>> {
+    let target = Target::open(path, false)?;
+    let (target, status) = match target.superproject_target(path.rev())? {
+        Some(super_target) => (super_target, "submodule".to_string()),
+        None => {
+            let status = working_tree_status(&target)?.map(|status| status.label());
+            (target, status.unwrap_or_default())
+        }
+    };
+    let deleted_in = match target.deleted_in {
+        Some(deletion) if date_only => {
+            format_date(target.repo.find_commit(deletion)?.time().seconds())
+        }
+        Some(deletion) => deletion.to_string(),
+        None => String::new(),
+    };
+
+    let patterns = RefPatterns::from_repo(&target.repo)?;
+    let commits = last_commits(&target, last)?;
+    if commits.is_empty() {
+        // Untracked paths have no history yet, the status is all there is to say
+        if !status.is_empty() && !target.in_head()? {
+            let mut row: Vec<Cell> = if date_only {
+                vec![String::new().into()]
+            } else {
+                let mut row: Vec<Cell> = (0..5).map(|_| String::new().into()).collect();
+                row.extend([Cell::PullRequests(Vec::new()), Cell::Issues(Vec::new())]);
+                row
+            };
+            row.extend([deleted_in.into(), status.into()]);
+            return Ok(vec![row]);
+        }
+        return Err(anyhow!("No commits found for path: {}", path));
+    }
+
+    Ok(commits
+        .iter()
+        .map(|commit| {
+            let date = format_date(commit.time().seconds());
+            if date_only {
+                return vec![
+                    date.into(),
+                    deleted_in.clone().into(),
+                    status.clone().into(),
+                ];
+            }
+            let author = commit.author();
+            let message = commit.message().unwrap_or("");
+            let [pull_requests, issues] = ref_cells(&patterns, message);
+            vec![
+                commit.id().to_string().into(),
+                author.name().unwrap_or("Unknown").to_string().into(),
+                author.email().unwrap_or("").to_string().into(),
+                date.into(),
+                message.trim_end().to_string().into(),
+                pull_requests,
+                issues,
+                deleted_in.clone().into(),
+                status.clone().into(),
+            ]
+        })
+        .collect())
+}
+
+/// One table row per line of a file, with the commit that last changed it
+/// Moved and copied lines are attributed to where they came from as far as `copies` looks
+pub fn blame_rows(
+    path: &TargetPath,
+    date_only: bool,
+    line_range: Option<(usize, usize)>,
+    copies: CopyDetection,
+) -> Result<Vec<Vec<Cell>>> {
+    let target = Target::open(path, true)?;
+    let repo = &target.repo;
+    let patterns = RefPatterns::from_repo(repo)?;
+
+    let blame = blame_at(repo, &target.relative_path, target.start, copies)
+        .map_err(|e| anyhow!("Failed to get blame for file '{}': {}", path, e))?;
+    let sources = copies.line_sources(repo, &blame, &target.relative_path)?;
+    let content = target.read_content()?;
+    let lines: Vec<&str> = content.lines().collect();
+
+    let (start, end) = shown_lines(path, line_range, lines.len())?;
+
+    let mut rows = Vec::new();
+    for line_num in start..=end {
+        let (commit, orig_path, orig_line) =
+            match (sources.get(&line_num), blame.get_line(line_num)) {
+                (Some(source), _) => (
+                    Some(repo.find_commit(source.commit_id)?),
+                    source.path.display().to_string(),
+                    Some(source.line),
+                ),
+                (None, Some(hunk)) => (
+                    Some(repo.find_commit(hunk.final_commit_id())?),
+                    hunk.path()
+                        .unwrap_or(&target.relative_path)
+                        .display()
+                        .to_string(),
+                    Some(hunk.orig_start_line() + line_num - hunk.final_start_line()),
+                ),
+                (None, None) => (None, String::new(), None),
+            };
+        let date = commit
+            .as_ref()
+            .map(|commit| format_date(commit.time().seconds()))
+            .unwrap_or_default();
+        let code = lines[line_num - 1].to_string();
+
+        rows.push(if date_only {
+            vec![date.into(), Cell::Line(Some(line_num)), code.into()]
+        } else {
+            let (hash, author, summary, [pull_requests, issues]) = match &commit {
+                Some(commit) => (
+                    commit.id().to_string(),
+                    commit.author().name().unwrap_or("Unknown").to_string(),
+                    commit.summary().unwrap_or("").to_string(),
+                    ref_cells(&patterns, commit.message().unwrap_or("")),
+                ),
+                None => (
+                    String::new(),
+                    "Unknown".to_string(),
+                    String::new(),
+                    [Cell::PullRequests(Vec::new()), Cell::Issues(Vec::new())],
+                ),
+            };
+            let mut row = vec![
+                hash.into(),
+                author.into(),
+                date.into(),
+                Cell::Line(Some(line_num)),
+                summary.into(),
+                pull_requests,
+                issues,
+            ];
+            if copies.is_enabled() {
+                row.extend([orig_path.into(), Cell::Line(orig_line)]);
+            }
+            row.push(code.into());
+            row
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Pull requests and issues mentioned in a commit message, as two cells
+fn ref_cells(patterns: &RefPatterns, message: &str) -> [Cell; 2] {
+    let refs = patterns.extract(message);
+    [
+        Cell::PullRequests(refs.pull_requests),
+        Cell::Issues(refs.issues),
+    ]
+}
+
+/// Render rows as CSV, TSV, a Markdown table (header first) or JSON
+/// The column named "code" is shown as inline code in Markdown
+pub fn render_table(headers: &[&str], rows: &[Vec<Cell>], format: Format) -> String {
+    if format == Format::Json {
+        return render_json(headers, rows);
+    }
+
+    let code_column = headers.iter().position(|&header| header == "code");
+    let render_row = |cells: &[String], header: bool| -> String {
+        match format {
+            Format::Tsv => cells
+                .iter()
+                .map(|cell| escape_tsv(cell))
+                .collect::<Vec<_>>()
+                .join("\t"),
+            Format::Markdown => {
+                let cells: Vec<String> = cells
+                    .iter()
+                    .enumerate()
+                    .map(|(index, cell)| {
+                        if !header && Some(index) == code_column {
+                            markdown_code(cell)
+                        } else {
+                            escape_markdown(cell)
+                        }
+                    })
+                    .collect();
+                format!("| {} |", cells.join(" | "))
+            }
+            _ => cells
+                .iter()
+                .map(|cell| escape_csv(cell))
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    };
+
+    let headers: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    let mut lines = vec![render_row(&headers, true)];
+    if format == Format::Markdown {
+        lines.push(format!("|{}", "---|".repeat(headers.len())));
+    }
+    lines.extend(rows.iter().map(|row| {
+        let cells: Vec<String> = row.iter().map(Cell::text).collect();
+        render_row(&cells, false)
+    }));
+    lines.join("\n")
+}
+
+/// An array with one object per row, keyed by column name; line numbers are numbers and
+/// pull requests and issues are arrays
+fn render_json(headers: &[&str], rows: &[Vec<Cell>]) -> String {
+    let objects: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            let object = headers
+                .iter()
+                .zip(row)
+                .map(|(&header, cell)| (header.to_string(), cell.json()))
+                .collect();
+            Value::Object(object)
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&objects).unwrap_or_default()
+}
+
+/// Quote a CSV field when it holds a comma, quote or line break, doubling inner quotes (RFC 4180)
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// TSV has no quoting, so tabs, line breaks and backslashes are written as escape sequences
+fn escape_tsv(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Keep a Markdown cell on one row: pipes and backslashes are escaped, line breaks become <br>
+fn escape_markdown(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Wrap code in a code span long enough to hold any backticks inside it; pipes still need
+/// escaping, as tables are split into cells before code spans are parsed
+fn markdown_code(code: &str) -> String {
+    if code.trim().is_empty() {
+        return String::new();
+    }
+
+    let mut longest = 0;
+    let mut run = 0;
+    for c in code.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest + 1);
+    // A space keeps a leading or trailing backtick from merging with the fence
+    let padding = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!(
+        "{fence}{padding}{}{padding}{fence}",
+        code.replace('|', "\\|")
+    )
+}
+
+fn format_date(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{commit_files, remove_files};
+    use git2::Repository;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(escape_csv("plain"), "plain");
+        assert_eq!(escape_csv("a, b"), "\"a, b\"");
+        assert_eq!(escape_csv("say \"hi\"\nthen"), "\"say \"\"hi\"\"\nthen\"");
+
+        assert_eq!(escape_tsv("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+
+        assert_eq!(escape_markdown("a | b\nc"), "a \\| b<br>c");
+        assert_eq!(markdown_code("    x || y"), "`    x \\|\\| y`");
+        assert_eq!(markdown_code("let s = `a``b`;"), "```let s = `a``b`;```");
+        assert_eq!(markdown_code("`tick`"), "`` `tick` ``");
+        assert_eq!(markdown_code("   "), "");
+    }
+
+    #[test]
+    fn test_tables_from_history() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("lib.rs", "fn a() {}\n")],
+            "Alice",
+            1749456964,
+            "add a, the first",
+        );
+        commit_files(
+            &repo,
+            &[("lib.rs", "fn a() {}\nlet x = a || b;\n")],
+            "Bob",
+            1749556964,
+            "add x\n\nBecause | reasons",
+        );
+
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "lib.rs".to_string(),
+        };
+
+        let rows = last_commit_rows(&target, false, Some(5)).unwrap();
+        let csv = render_table(&commit_headers(false), &rows, Format::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "commit,author,email,date,message,pull_requests,issues,deleted_in,status"
+        );
+        assert!(lines[1].ends_with(",Bob,bob@example.com,2025-06-10,\"add x"));
+        assert_eq!(lines[3], "Because | reasons\",,,,");
+        assert!(lines[4].ends_with(",Alice,alice@example.com,2025-06-09,\"add a, the first\",,,,"));
+
+        let rows = last_commit_rows(&target, true, None).unwrap();
+        assert_eq!(rows, vec![vec!["2025-06-10", "", ""]]);
+
+        let rows = blame_rows(&target, false, Some((2, 2)), CopyDetection::default()).unwrap();
+        let markdown = render_table(
+            &blame_headers(false, CopyDetection::default()),
+            &rows,
+            Format::Markdown,
+        );
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(
+            lines[0],
+            "| commit | author | date | line | summary | pull_requests | issues | code |"
+        );
+        assert_eq!(lines[1], "|---|---|---|---|---|---|---|---|");
+        assert!(
+            lines[2].ends_with("| Bob | 2025-06-10 | 2 | add x |  |  | `let x = a \\|\\| b;` |")
+        );
+        assert_eq!(lines.len(), 3);
+
+        let rows = blame_rows(&target, true, None, CopyDetection::default()).unwrap();
+        let tsv = render_table(
+            &blame_headers(true, CopyDetection::default()),
+            &rows,
+            Format::Tsv,
+        );
+        assert_eq!(
+            tsv,
+            "date\tline\tcode\n2025-06-09\t1\tfn a() {}\n2025-06-10\t2\tlet x = a || b;"
+        );
+
+        // An empty file has no rows
+        commit_files(&repo, &[("empty", "")], "Alice", 1749656964, "add empty");
+        let empty = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "empty".to_string(),
+        };
+        let rows = blame_rows(&empty, false, None, CopyDetection::default()).unwrap();
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_refs_columns_and_json() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("lib.rs", "fn a() {}\n")],
+            "Alice",
+            1749456964,
+            "Fix login (#42)\n\nPLAT-7, PLAT-8",
+        );
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "lib.rs".to_string(),
+        };
+
+        let rows = last_commit_rows(&target, false, None).unwrap();
+        assert_eq!(rows[0][5..7], ["#42", "PLAT-7, PLAT-8"]);
+
+        let rows = blame_rows(&target, false, None, CopyDetection::default()).unwrap();
+        let json = render_table(
+            &blame_headers(false, CopyDetection::default()),
+            &rows,
+            Format::Json,
+        );
+        let lines: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(lines[0]["line"], 1);
+        assert_eq!(lines[0]["pull_requests"], serde_json::json!([42]));
+        assert_eq!(lines[0]["issues"], serde_json::json!(["PLAT-7", "PLAT-8"]));
+        assert_eq!(lines[0]["code"], "fn a() {}");
+
+        // References are kept whole, even when they hold the cell separator or a "#"
+        repo.config()
+            .unwrap()
+            .set_str("wer.issuePattern", r"\[(.+?)\]")
+            .unwrap();
+        commit_files(
+            &repo,
+            &[("lib.rs", "fn b() {}\n")],
+            "Bob",
+            1749556964,
+            "Rename [see #1, then #2]",
+        );
+        let rows = last_commit_rows(&target, false, None).unwrap();
+        let json = render_table(&commit_headers(false), &rows, Format::Json);
+        let commits: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(commits[0]["issues"], serde_json::json!(["see #1, then #2"]));
+        assert_eq!(commits[0]["pull_requests"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_deleted_and_untracked_rows() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_files(
+            &repo,
+            &[("gone.txt", "one\n")],
+            "Alice",
+            1749456964,
+            "add gone",
+        );
+        let deletion = remove_files(&repo, &["gone.txt"], "Bob", 1749556964, "delete gone");
+        let target =
+            |name: &str| TargetPath::Workdir(dir.path().join(name).to_string_lossy().to_string());
+
+        // The rows describe the file before deletion, and say who deleted it
+        let rows = last_commit_rows(&target("gone.txt"), false, None).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][1], "Alice");
+        assert_eq!(rows[0][7], deletion.to_string().as_str());
+        let rows = last_commit_rows(&target("gone.txt"), true, None).unwrap();
+        assert_eq!(rows, vec![vec!["2025-06-09", "2025-06-10", ""]]);
+
+        std::fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        let rows = last_commit_rows(&target("new.txt"), false, None).unwrap();
+        assert_eq!(
+            rows,
+            vec![vec!["", "", "", "", "", "", "", "", "untracked"]]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use git2::{IndexEntry, IndexTime, Oid, Repository, Signature, Time};
+
+/// Commit files on top of HEAD as the given author, at the given Unix timestamp
+/// Files are written to the working copy too when the repository has one
+pub fn commit_files(
+    repo: &Repository,
+    files: &[(&str, &str)],
+    author: &str,
+    timestamp: i64,
+    message: &str,
+) -> Oid {
+    stage_files(repo, files);
+    commit_index(repo, author, timestamp, message)
+}
+
+/// Add files to the index without committing them, e.g. to resolve a merge
+/// Files are written to the working copy too when the repository has one
+pub fn stage_files(repo: &Repository, files: &[(&str, &str)]) {
+    let mut index = repo.index().unwrap();
+
+    for (path, content) in files {
+        if let Some(workdir) = repo.workdir() {
+            let full_path = workdir.join(path);
+            std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            std::fs::write(&full_path, content).unwrap();
+        }
+
+        let entry = IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: content.len() as u32,
+            id: Oid::zero(),
+            flags: 0,
+            flags_extended: 0,
+            path: path.as_bytes().to_vec(),
+        };
+        index.add_frombuffer(&entry, content.as_bytes()).unwrap();
+    }
+
+    if repo.workdir().is_some() {
+        index.write().unwrap();
+    }
+}
+
+/// Commit the removal of files on top of HEAD
+pub fn remove_files(
+    repo: &Repository,
+    paths: &[&str],
+    author: &str,
+    timestamp: i64,
+    message: &str,
+) -> Oid {
+    let mut index = repo.index().unwrap();
+
+    for path in paths {
+        if let Some(workdir) = repo.workdir() {
+            std::fs::remove_file(workdir.join(path)).unwrap();
+        }
+        index.remove_path(std::path::Path::new(path)).unwrap();
+    }
+
+    if repo.workdir().is_some() {
+        index.write().unwrap();
+    }
+
+    commit_index(repo, author, timestamp, message)
+}
+
+/// Commit the repository's current index on top of HEAD
+pub fn commit_index(repo: &Repository, author: &str, timestamp: i64, message: &str) -> Oid {
+    commit_index_with_parents(repo, &[], author, timestamp, message)
+}
+
+/// Commit the repository's current index as a merge of HEAD and another commit
+pub fn commit_merge(
+    repo: &Repository,
+    other: Oid,
+    author: &str,
+    timestamp: i64,
+    message: &str,
+) -> Oid {
+    commit_index_with_parents(repo, &[other], author, timestamp, message)
+}
+
+fn commit_index_with_parents(
+    repo: &Repository,
+    other_parents: &[Oid],
+    author: &str,
+    timestamp: i64,
+    message: &str,
+) -> Oid {
+    let mut index = repo.index().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+    let email = format!("{}@example.com", author.to_lowercase().replace(' ', "."));
+    let signature = Signature::new(author, &email, &Time::new(timestamp, 0)).unwrap();
+
+    let mut parents: Vec<git2::Commit> = repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    for &other in other_parents {
+        parents.push(repo.find_commit(other).unwrap());
+    }
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap()
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Months, Utc};
+use std::collections::HashMap;
+
+use crate::cli::TimelinePeriod;
+use crate::git::{ColorScheme, Target, TargetPath, commit_touches_path};
+
+/// Sparkline levels, from a single commit to the busiest period
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How many authors get their own row with --by-author
+const TOP_AUTHORS: usize = 5;
+
+impl TimelinePeriod {
+    /// Number of periods shown, ending with the current one
+    fn count(self) -> usize {
+        match self {
+            TimelinePeriod::Week => 52,
+            TimelinePeriod::Month => 24,
+        }
+    }
+
+    /// Index of the period a timestamp falls in, counting back from the current period (0)
+    fn periods_ago(self, now: i64, timestamp: i64) -> Option<usize> {
+        match self {
+            TimelinePeriod::Week => usize::try_from((now - timestamp) / (7 * 24 * 60 * 60)).ok(),
+            TimelinePeriod::Month => {
+                let now = DateTime::from_timestamp(now, 0)?;
+                let date = DateTime::from_timestamp(timestamp, 0)?;
+                let months =
+                    (now.year() - date.year()) * 12 + now.month() as i32 - date.month() as i32;
+                usize::try_from(months).ok()
+            }
+        }
+    }
+
+    /// Label for the start of the period `ago` periods back
+    fn label(self, now: i64, ago: usize) -> String {
+        let now = DateTime::from_timestamp(now, 0).unwrap_or_else(Utc::now);
+        match self {
+            TimelinePeriod::Week => (now - chrono::Duration::weeks(ago as i64))
+                .format("%d %b %Y")
+                .to_string(),
+            TimelinePeriod::Month => now
+                .checked_sub_months(Months::new(ago as u32))
+                .unwrap_or(now)
+                .format("%b %Y")
+                .to_string(),
+        }
+    }
+}
+
+/// Chart the commits that touched a path per week or month, optionally per top author
+pub fn get_timeline(
+    path: &TargetPath,
+    period: TimelinePeriod,
+    by_author: bool,
+    no_color: bool,
+) -> Result<String> {
+    timeline_at(path, period, by_author, no_color, Utc::now().timestamp())
+}
+
+fn timeline_at(
+    path: &TargetPath,
+    period: TimelinePeriod,
+    by_author: bool,
+    no_color: bool,
+    now: i64,
+) -> Result<String> {
+    let target = Target::open(path, false)?;
+    let repo = &target.repo;
+    let colors = ColorScheme::new(no_color);
+    let count = period.count();
+
+    let mut revwalk = target.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+
+    // Counts per period, oldest first
+    let mut total = vec![0; count];
+    let mut authors: HashMap<String, Vec<usize>> = HashMap::new();
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        let Some(ago) = period.periods_ago(now, commit.time().seconds()) else {
+            // Committed "in the future", clocks aren't always right
+            continue;
+        };
+        if ago >= count {
+            break;
+        }
+        if !commit_touches_path(repo, &commit, &target.relative_path)? {
+            continue;
+        }
+
+        let index = count - 1 - ago;
+        total[index] += 1;
+        let author = commit.author().name().unwrap_or("Unknown").to_string();
+        authors.entry(author).or_insert_with(|| vec![0; count])[index] += 1;
+    }
+
+    let commits: usize = total.iter().sum();
+    let max = total.iter().copied().max().unwrap_or(0);
+    let unit = match period {
+        TimelinePeriod::Week => "week",
+        TimelinePeriod::Month => "month",
+    };
+
+    let mut rows = vec![("all".to_string(), total)];
+    if by_author {
+        let mut ranked: Vec<(String, Vec<usize>)> = authors.into_iter().collect();
+        ranked.sort_by(|a, b| {
+            let (a_total, b_total) = (a.1.iter().sum::<usize>(), b.1.iter().sum::<usize>());
+            b_total.cmp(&a_total).then(a.0.cmp(&b.0))
+        });
+        rows.extend(ranked.into_iter().take(TOP_AUTHORS));
+    }
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(15);
+
+    let mut result = format!(
+        "Commits per {} touching {} ({} in the last {} {}s)",
+        unit, path, commits, count, unit
+    );
+    for (index, (name, counts)) in rows.iter().enumerate() {
+        let color = if index == 0 {
+            colors.added
+        } else {
+            colors.commit
+        };
+        let name: String = name.chars().take(width).collect();
+        result.push_str(&format!(
+            "\n{:<width$} {}{}{} │ {}",
+            name,
+            color,
+            sparkline(counts, max),
+            colors.reset,
+            counts.iter().sum::<usize>(),
+            width = width
+        ));
+    }
+
+    // Start and end labels under the chart
+    let first = period.label(now, count - 1);
+    let last = period.label(now, 0);
+    let gap = count.saturating_sub(first.chars().count() + last.chars().count());
+    result.push_str(&format!(
+        "\n{:<width$} {}{}{}{}{}",
+        "",
+        colors.date,
+        first,
+        " ".repeat(gap),
+        last,
+        colors.reset,
+        width = width
+    ));
+
+    if commits == 0 {
+        result.push_str(&format!(
+            "\nNo commits in the last {} {}s: this path looks inactive",
+            count, unit
+        ));
+    }
+
+    Ok(result)
+}
+
+/// One character per period, scaled so `max` gets the full bar; empty periods are blank
+fn sparkline(counts: &[usize], max: usize) -> String {
+    counts
+        .iter()
+        .map(|&count| {
+            if count == 0 || max == 0 {
+                ' '
+            } else {
+                BARS[((count * BARS.len()).div_ceil(max)).clamp(1, BARS.len()) - 1]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::commit_files;
+    use git2::Repository;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sparkline_scaling() {
+        assert_eq!(sparkline(&[0, 1, 4, 8], 8), " ▁▄█");
+        assert_eq!(sparkline(&[0, 0], 0), "  ");
+    }
+
+    #[test]
+    fn test_timeline_by_month_and_author() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        // Two commits in June 2025, one in August 2025
+        commit_files(&repo, &[("lib.rs", "1\n")], "Alice", 1749456964, "one");
+        commit_files(&repo, &[("lib.rs", "2\n")], "Bob", 1749556964, "two");
+        commit_files(&repo, &[("other.rs", "x\n")], "Bob", 1754556964, "other");
+        commit_files(&repo, &[("lib.rs", "3\n")], "Alice", 1754656964, "three");
+
+        let target = TargetPath::Tree {
+            git_dir: dir.path().to_path_buf(),
+            rev: None,
+            path: "lib.rs".to_string(),
+        };
+        // "Now" is mid October 2025
+        let now = 1760500000;
+        let output = timeline_at(&target, TimelinePeriod::Month, true, true, now).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "Commits per month touching lib.rs (3 in the last 24 months)"
+        );
+        // Months are oldest first: June 2025 is 4 months before October, August 2
+        assert_eq!(&lines[1][..6], "all   ");
+        assert_eq!(&lines[1][6..], format!("{}█ ▄   │ 3", " ".repeat(19)));
+        assert!(lines[2].starts_with("Alice ") && lines[2].ends_with("▄ ▄   │ 2"));
+        assert!(lines[3].starts_with("Bob   ") && lines[3].ends_with("▄     │ 1"));
+        assert!(lines[4].trim_start().starts_with("Nov 2023"), "{}", output);
+        assert!(lines[4].ends_with("Oct 2025"), "{}", output);
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 
 pub fn format_timestamp_day_month(timestamp: i64) -> String {
@@ -14,10 +15,349 @@ pub fn format_timestamp_day_month_year(timestamp: i64) -> String {
     dt.format("%d %b %Y").to_string()
 }
 
+/// Decode %XX escapes in a URI or URL path
+pub fn percent_decode(input: &str) -> Result<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%'
+            && let Some(byte) = input
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            index += 3;
+            continue;
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| anyhow!("Invalid UTF-8 in '{}'", input))
+}
+
+/// Escape everything but unreserved characters and "/" for use in a URL path
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Parse a --since value into a Unix timestamp: a date like "2025-01-31", or a period
+/// before `now` like "90d", "6 weeks", "3 months" or "1 year ago"
+pub fn parse_since(input: &str, now: i64) -> Result<i64> {
+    let input = input.trim();
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp());
+    }
+
+    let period = input.trim_end_matches("ago").trim();
+    let split = period
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(period.len());
+    let (count, unit) = period.split_at(split);
+    let count: i64 = count.parse().map_err(|_| {
+        anyhow!(
+            "Invalid --since '{}'. Use a date like 2025-01-31 or a period like 90d, 6 weeks, 1 year",
+            input
+        )
+    })?;
+
+    let days = match unit.trim().trim_end_matches('s') {
+        "d" | "day" => 1,
+        "w" | "week" => 7,
+        "m" | "month" => 30,
+        "y" | "year" => 365,
+        unit => return Err(anyhow!("Unknown --since unit '{}' in '{}'", unit, input)),
+    };
+
+    Ok(now - count * days * 24 * 60 * 60)
+}
+
+/// Parse a line range like "40,60", "40,+5" (5 lines from 40) or "42" (a single line)
+/// Returns 1-based inclusive start and end lines
+pub fn parse_line_range(input: &str) -> Result<(usize, usize)> {
+    let parse_line = |value: &str| -> Result<usize> {
+        value
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|line| *line > 0)
+            .ok_or_else(|| anyhow!("Invalid line number '{}' in range '{}'", value, input))
+    };
+
+    let (start, end) = match input.split_once(',') {
+        Some((start, end)) => {
+            let start = parse_line(start)?;
+            let end = match end.trim().strip_prefix('+') {
+                Some(count) => start + parse_line(count)? - 1,
+                None => parse_line(end)?,
+            };
+            (start, end)
+        }
+        None => {
+            let line = parse_line(input)?;
+            (line, line)
+        }
+    };
+
+    if end < start {
+        return Err(anyhow!(
+            "Invalid line range '{}': end is before start",
+            input
+        ));
+    }
+
+    Ok((start, end))
+}
+
+/// The lines of a file of `line_count` lines to show: the range given with -L, cut off at the
+/// end of the file, or all of them (none for an empty file)
+/// Only an explicit range that starts past the end is an error
+pub fn shown_lines(
+    path: &impl std::fmt::Display,
+    line_range: Option<(usize, usize)>,
+    line_count: usize,
+) -> Result<(usize, usize)> {
+    match line_range {
+        Some((start, _)) if start > line_count => Err(anyhow!(
+            "Line {} is past the end of '{}' ({} lines)",
+            start,
+            path,
+            line_count
+        )),
+        Some((start, end)) => Ok((start, end.min(line_count))),
+        None => Ok((1, line_count)),
+    }
+}
+
+/// Expand a leading `~` or `~user` and any `$VAR` / `${VAR}` references in a path
+/// Quotes and spaces are part of the name, the shell has already removed its own quoting
+pub fn expand_path(input: &str) -> Result<String> {
+    expand_path_with(input, &|name| std::env::var(name).ok(), &home_dir_for_user)
+}
+
+/// Expansion with injectable lookups so it can be tested without touching the real environment
+fn expand_path_with(
+    input: &str,
+    lookup_var: &dyn Fn(&str) -> Option<String>,
+    lookup_user_home: &dyn Fn(&str) -> Option<String>,
+) -> Result<String> {
+    // Expand the tilde prefix: "~", "~/rest", "~user" or "~user/rest"
+    let tilde_expanded = if let Some(rest) = input.strip_prefix('~') {
+        let (user, remainder) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, ""),
+        };
+
+        let home = if user.is_empty() {
+            lookup_var("HOME")
+                .or_else(|| lookup_var("USERPROFILE"))
+                .ok_or_else(|| anyhow!("Cannot expand '~': HOME is not set"))?
+        } else {
+            lookup_user_home(user)
+                .ok_or_else(|| anyhow!("Cannot expand '~{}': unknown user '{}'", user, user))?
+        };
+
+        format!("{}{}", home.trim_end_matches('/'), remainder)
+    } else {
+        input.to_string()
+    };
+
+    // Expand $VAR and ${VAR} references
+    let mut result = String::with_capacity(tilde_expanded.len());
+    let mut chars = tilde_expanded.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        }
+
+        let mut name = String::new();
+        while let Some(&next) = chars.peek() {
+            let valid = next == '_'
+                || next.is_ascii_alphabetic()
+                || (!name.is_empty() && next.is_ascii_digit());
+            if !valid {
+                break;
+            }
+            name.push(next);
+            chars.next();
+        }
+
+        if braced && chars.next() != Some('}') {
+            return Err(anyhow!("Unterminated '${{' in path '{}'", input));
+        }
+
+        // A lone '$' isn't a variable reference, keep it as-is
+        if name.is_empty() {
+            if braced {
+                return Err(anyhow!("Empty variable name '${{}}' in path '{}'", input));
+            }
+            result.push('$');
+            continue;
+        }
+
+        let value = lookup_var(&name).ok_or_else(|| {
+            anyhow!(
+                "Cannot expand '${}' in path '{}': environment variable is not set",
+                name,
+                input
+            )
+        })?;
+        result.push_str(&value);
+    }
+
+    Ok(result)
+}
+
+/// Look up another user's home directory in /etc/passwd
+#[cfg(unix)]
+fn home_dir_for_user(user: &str) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() >= 6 && fields[0] == user {
+            Some(fields[5].to_string())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(not(unix))]
+fn home_dir_for_user(_user: &str) -> Option<String> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_percent_encoding() {
+        assert_eq!(
+            percent_encode_path("src/my file#1.rs"),
+            "src/my%20file%231.rs"
+        );
+        assert_eq!(
+            percent_decode("src/my%20file%231.rs").unwrap(),
+            "src/my file#1.rs"
+        );
+        assert_eq!(percent_decode("100%").unwrap(), "100%");
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = 1749456964;
+        assert_eq!(parse_since("2025-06-09", now).unwrap(), 1749427200);
+        assert_eq!(parse_since("90d", now).unwrap(), now - 90 * 86400);
+        assert_eq!(parse_since("6 weeks", now).unwrap(), now - 42 * 86400);
+        assert_eq!(parse_since("1 year ago", now).unwrap(), now - 365 * 86400);
+        assert!(parse_since("yesterday", now).is_err());
+        assert!(parse_since("3 fortnights", now).is_err());
+    }
+
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("40,60").unwrap(), (40, 60));
+        assert_eq!(parse_line_range("42").unwrap(), (42, 42));
+        assert_eq!(parse_line_range("40,+5").unwrap(), (40, 44));
+        assert!(parse_line_range("60,40").is_err());
+        assert!(parse_line_range("0,4").is_err());
+        assert!(parse_line_range("a,b").is_err());
+    }
+
+    #[test]
+    fn test_shown_lines() {
+        assert_eq!(shown_lines(&"f", None, 3).unwrap(), (1, 3));
+        assert_eq!(shown_lines(&"f", Some((2, 9)), 3).unwrap(), (2, 3));
+        // An empty file has no lines to show, which only matters when some were asked for
+        assert_eq!(shown_lines(&"f", None, 0).unwrap(), (1, 0));
+        let error = shown_lines(&"f", Some((1, 1)), 0).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1 is past the end of 'f' (0 lines)");
+    }
+
+    fn test_var(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/alice".to_string()),
+            "PROJECT" => Some("work/wer".to_string()),
+            _ => None,
+        }
+    }
+
+    fn test_user_home(user: &str) -> Option<String> {
+        match user {
+            "bob" => Some("/home/bob".to_string()),
+            _ => None,
+        }
+    }
+
+    fn expand(input: &str) -> Result<String> {
+        expand_path_with(input, &test_var, &test_user_home)
+    }
+
+    #[test]
+    fn test_expand_path_tilde() {
+        assert_eq!(expand("~").unwrap(), "/home/alice");
+        assert_eq!(expand("~/file.txt").unwrap(), "/home/alice/file.txt");
+    }
+
+    #[test]
+    fn test_expand_path_tilde_user() {
+        assert_eq!(expand("~bob/notes.md").unwrap(), "/home/bob/notes.md");
+        assert_eq!(expand("~bob").unwrap(), "/home/bob");
+        assert!(expand("~nobody/file").is_err());
+    }
+
+    #[test]
+    fn test_expand_path_env_vars() {
+        assert_eq!(expand("$HOME/src").unwrap(), "/home/alice/src");
+        assert_eq!(
+            expand("${PROJECT}/Cargo.toml").unwrap(),
+            "work/wer/Cargo.toml"
+        );
+        assert_eq!(expand("~/$PROJECT").unwrap(), "/home/alice/work/wer");
+    }
+
+    #[test]
+    fn test_expand_path_unset_var() {
+        let result = expand("$MISSING/file");
+        assert!(result.unwrap_err().to_string().contains("MISSING"));
+        assert!(expand("${PROJECT").is_err());
+    }
+
+    #[test]
+    fn test_expand_path_quoted_inputs() {
+        // What "wer '~/my file.txt'" passes once the shell removed its quotes
+        assert_eq!(expand("~/my file.txt").unwrap(), "/home/alice/my file.txt");
+        assert_eq!(
+            expand("$HOME/my file.txt").unwrap(),
+            "/home/alice/my file.txt"
+        );
+        // Quotes and spaces that reach wer belong to the name
+        assert_eq!(expand("\"src/main.rs\"").unwrap(), "\"src/main.rs\"");
+        assert_eq!(expand(" notes.md ").unwrap(), " notes.md ");
+    }
+
+    #[test]
+    fn test_expand_path_plain() {
+        assert_eq!(expand("src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(expand("price$.txt").unwrap(), "price$.txt");
+        assert_eq!(expand("dir~/file").unwrap(), "dir~/file");
+    }
+
     #[test]
     fn test_format_timestamp_day_month() {
         let timestamp = 1749456964;
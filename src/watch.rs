@@ -0,0 +1,314 @@
+use anyhow::{Result, anyhow};
+use git2::{Oid, Repository};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::copies::CopyDetection;
+use crate::git::{
+    ColorScheme, Origin, OutputOptions, Target, TargetPath, get_blame, get_last_commit,
+};
+use crate::porcelain::{line_origins, map_lines};
+
+/// How long to wait for more changes before re-rendering, so a rebase or an editor's
+/// write-and-rename is shown once
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// The commit behind every line of one version of a file
+struct Attribution {
+    content: String,
+    commits: Vec<Oid>,
+}
+
+/// Show the blame of a file and re-render it whenever the file, HEAD or a ref changes
+/// Lines that are new or now attributed to a different commit are marked in the left border
+pub fn watch_blame(
+    path: &TargetPath,
+    options: OutputOptions,
+    line_range: Option<(usize, usize)>,
+) -> Result<()> {
+    let mut previous: Option<Attribution> = None;
+    watch(path, true, || {
+        let current = attribution(path, options.copies)?;
+        let changed = match &previous {
+            Some(previous) => changed_lines(previous, &current)?,
+            None => HashSet::new(),
+        };
+        previous = Some(current);
+        get_blame(path, options, line_range, Some(&changed))
+    })
+}
+
+/// Show the last commit of a path and re-render it whenever the path, HEAD or a ref changes
+/// Output lines that weren't in the previous render are marked in the left margin
+pub fn watch_last_commit(
+    path: &TargetPath,
+    options: OutputOptions,
+    last: Option<usize>,
+    recurse_submodules: bool,
+    origin: Origin,
+) -> Result<()> {
+    let colors = ColorScheme::new(options.no_color);
+    let mut previous: Option<String> = None;
+    watch(path, false, || {
+        let current = get_last_commit(path, options, last, recurse_submodules, origin)?;
+        let marked = mark_new_lines(previous.as_deref(), &current, &colors);
+        previous = Some(current);
+        Ok(marked)
+    })
+}
+
+/// Render once, then again after every relevant change until interrupted
+fn watch(
+    path: &TargetPath,
+    must_be_file: bool,
+    mut render: impl FnMut() -> Result<String>,
+) -> Result<()> {
+    let target = Target::open(path, must_be_file)?;
+    let filter = ChangeFilter::new(target)?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for (watched, mode) in filter.watched_paths() {
+        watcher
+            .watch(&watched, mode)
+            .map_err(|e| anyhow!("Cannot watch '{}': {}", watched.display(), e))?;
+    }
+
+    print_frame(path, render());
+    loop {
+        let event = receiver
+            .recv()
+            .map_err(|_| anyhow!("File watcher stopped"))??;
+        if !filter.is_relevant(&event) {
+            continue;
+        }
+        while receiver.recv_timeout(SETTLE_TIME).is_ok() {}
+        print_frame(path, render());
+    }
+}
+
+/// Decides which file system events should trigger a re-render
+struct ChangeFilter {
+    repo: Repository,
+    /// The watched path on disk, unless it is read from a repository tree
+    full_path: Option<PathBuf>,
+    is_dir: bool,
+    /// Where HEAD lives (per worktree)
+    git_dir: PathBuf,
+    /// Where refs and packed-refs live (shared between worktrees)
+    common_dir: PathBuf,
+}
+
+impl ChangeFilter {
+    fn new(target: Target) -> Result<Self> {
+        let full_path = target
+            .full_path
+            .as_deref()
+            .map(Path::canonicalize)
+            .transpose()?;
+        let is_dir = full_path.as_deref().is_some_and(Path::is_dir);
+        let git_dir = target.repo.path().canonicalize()?;
+        let common_dir = common_dir(&git_dir)?;
+        Ok(Self {
+            repo: target.repo,
+            full_path,
+            is_dir,
+            git_dir,
+            common_dir,
+        })
+    }
+
+    /// Directories to watch; for a file its directory is watched, since editors often save by
+    /// replacing the file, which would end a watch on the file itself
+    fn watched_paths(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut paths = vec![(self.git_dir.clone(), RecursiveMode::NonRecursive)];
+        if self.common_dir != self.git_dir {
+            paths.push((self.common_dir.clone(), RecursiveMode::NonRecursive));
+        }
+        paths.push((self.common_dir.join("refs"), RecursiveMode::Recursive));
+
+        match (&self.full_path, self.is_dir) {
+            (Some(dir), true) => paths.push((dir.clone(), RecursiveMode::Recursive)),
+            (Some(file), false) => {
+                if let Some(parent) = file.parent() {
+                    paths.push((parent.to_path_buf(), RecursiveMode::NonRecursive));
+                }
+            }
+            (None, _) => {}
+        }
+        paths
+    }
+
+    fn is_relevant(&self, event: &notify::Event) -> bool {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return false;
+        }
+        event.paths.iter().any(|path| self.is_relevant_path(path))
+    }
+
+    fn is_relevant_path(&self, path: &Path) -> bool {
+        // Git writes refs to a .lock file first and renames it into place
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "lock")
+        {
+            return false;
+        }
+        if path.starts_with(self.common_dir.join("refs"))
+            || path == self.git_dir.join("HEAD")
+            || path == self.common_dir.join("packed-refs")
+        {
+            return true;
+        }
+        if path.starts_with(&self.git_dir) || path.starts_with(&self.common_dir) {
+            return false;
+        }
+
+        match &self.full_path {
+            Some(dir) if self.is_dir => {
+                // Directories are reported as they fill up; only the files in them count
+                path.starts_with(dir) && !path.is_dir() && !self.is_ignored(path)
+            }
+            Some(file) => path == file,
+            None => false,
+        }
+    }
+
+    /// Build output and other ignored files inside a watched directory don't count
+    fn is_ignored(&self, path: &Path) -> bool {
+        let Some(relative) = self
+            .repo
+            .workdir()
+            .and_then(|workdir| workdir.canonicalize().ok())
+            .and_then(|workdir| path.strip_prefix(workdir).ok().map(Path::to_path_buf))
+        else {
+            return false;
+        };
+        self.repo.status_should_ignore(&relative).unwrap_or(false)
+    }
+}
+
+/// The directory shared by all worktrees, named by a linked worktree's "commondir" file
+fn common_dir(git_dir: &Path) -> Result<PathBuf> {
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => Ok(git_dir.join(common_dir.trim()).canonicalize()?),
+        Err(_) => Ok(git_dir.to_path_buf()),
+    }
+}
+
+/// Per-line commits of a file's current content, with uncommitted lines attributed to the
+/// zero commit
+fn attribution(path: &TargetPath, copies: CopyDetection) -> Result<Attribution> {
+    let target = Target::open(path, true)?;
+    let content = target.read_content()?;
+    let origins = line_origins(&target, &content, 1, content.lines().count(), copies)?;
+    Ok(Attribution {
+        commits: origins.iter().map(|origin| origin.commit_id).collect(),
+        content,
+    })
+}
+
+/// Line numbers of `current` that are new, or whose commit differs from the same line in
+/// `previous`
+fn changed_lines(previous: &Attribution, current: &Attribution) -> Result<HashSet<usize>> {
+    let mapping = map_lines(
+        previous.content.as_bytes(),
+        current.content.as_bytes(),
+        current.commits.len(),
+    )?;
+
+    let mut changed = HashSet::new();
+    for (index, previous_line) in mapping.into_iter().enumerate() {
+        let previous_commit = previous_line.and_then(|line| previous.commits.get(line - 1));
+        if previous_commit != Some(&current.commits[index]) {
+            changed.insert(index + 1);
+        }
+    }
+    Ok(changed)
+}
+
+/// Indent every line, marking the ones that weren't in the previous render
+fn mark_new_lines(previous: Option<&str>, current: &str, colors: &ColorScheme) -> String {
+    let previous_lines: HashSet<&str> = previous.map(|p| p.lines().collect()).unwrap_or_default();
+    let mut marked = String::new();
+    for line in current.lines() {
+        if previous.is_some() && !previous_lines.contains(line) {
+            marked.push_str(&format!("{}┃{} ", colors.added, colors.reset));
+        } else {
+            marked.push_str("  ");
+        }
+        marked.push_str(line);
+        marked.push('\n');
+    }
+    marked
+}
+
+/// Replace the screen with a header and the latest output
+fn print_frame(path: &TargetPath, output: Result<String>) {
+    let mut stdout = std::io::stdout().lock();
+    let clear = if stdout.is_terminal() {
+        "\x1b[2J\x1b[H"
+    } else {
+        ""
+    };
+    let body = match output {
+        Ok(output) => output,
+        Err(e) => format!("Error: {}\n", e),
+    };
+    // A closed stdout ends the program through the next write anyway
+    let _ = write!(
+        stdout,
+        "{}Watching {} · updated {} · Ctrl+C to stop\n\n{}\n",
+        clear,
+        path,
+        chrono::Local::now().format("%H:%M:%S"),
+        body
+    );
+    let _ = stdout.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_lines() {
+        let a = Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        let b = Oid::from_str("2222222222222222222222222222222222222222").unwrap();
+        let previous = Attribution {
+            content: "one\ntwo\nthree\n".to_string(),
+            commits: vec![a, a, a],
+        };
+
+        // An inserted line is new; the lines it pushes down keep their commit
+        let current = Attribution {
+            content: "zero\none\ntwo\nthree\n".to_string(),
+            commits: vec![Oid::zero(), a, a, a],
+        };
+        assert_eq!(
+            changed_lines(&previous, &current).unwrap(),
+            HashSet::from([1])
+        );
+
+        // Committing changes the attribution of an unchanged line
+        let current = Attribution {
+            content: "one\ntwo\nthree\n".to_string(),
+            commits: vec![a, b, a],
+        };
+        assert_eq!(
+            changed_lines(&previous, &current).unwrap(),
+            HashSet::from([2])
+        );
+    }
+
+    #[test]
+    fn test_mark_new_lines() {
+        let colors = ColorScheme::new(true);
+        assert_eq!(mark_new_lines(None, "a\nb", &colors), "  a\n  b\n");
+        assert_eq!(mark_new_lines(Some("a\nb"), "a\nc", &colors), "  a\n┃ c\n");
+    }
+}
@@ -0,0 +1,6 @@
+// The unit tests' repository helpers, so both kinds of tests build history the same way
+#[allow(dead_code)]
+#[path = "../../src/test_utils.rs"]
+mod test_utils;
+
+pub use test_utils::commit_files;
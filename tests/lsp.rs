@@ -0,0 +1,163 @@
+mod common;
+
+use common::commit_files;
+use git2::Repository;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// A `wer lsp` process driven over its stdin and stdout
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_wer"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Send a request and return its result, skipping requests from the server
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive();
+            if message.get("method").is_none() && message["id"] == json!(id) {
+                return message;
+            }
+        }
+    }
+}
+
+#[test]
+fn lsp_annotates_blame_and_updates_on_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    commit_files(
+        &repo,
+        &[("lib.rs", "one\ntwo\n")],
+        "Alice",
+        1749456964,
+        "add lib\n\nDetails",
+    );
+    commit_files(
+        &repo,
+        &[("lib.rs", "one\nTWO\n")],
+        "Bob",
+        1749556964,
+        "shout",
+    );
+    let path = dir.path().canonicalize().unwrap().join("lib.rs");
+    let uri = format!("file://{}", path.display());
+    let document = json!({ "uri": uri });
+
+    let mut client = Client::start();
+    let initialized = client.request(
+        "initialize",
+        json!({ "capabilities": { "workspace": { "codeLens": { "refreshSupport": true } } } }),
+    );
+    let capabilities = &initialized["result"]["capabilities"];
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["inlayHintProvider"], true);
+    client.notify("initialized", json!({}));
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": uri, "languageId": "rust", "version": 1, "text": "one\nTWO\n" } }),
+    );
+
+    // One lens per block of lines from the same commit
+    let lenses = client.request("textDocument/codeLens", json!({ "textDocument": document }));
+    let lenses = lenses["result"].as_array().unwrap();
+    assert_eq!(lenses.len(), 2);
+    assert_eq!(lenses[0]["range"]["start"]["line"], 0);
+    assert_eq!(
+        lenses[0]["command"]["title"],
+        "Alice, 09 Jun 2025 · add lib"
+    );
+    assert_eq!(lenses[1]["command"]["title"], "Bob, 10 Jun 2025 · shout");
+
+    // A hint at the end of every requested line
+    let hints = client.request(
+        "textDocument/inlayHint",
+        json!({
+            "textDocument": document,
+            "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 3 } }
+        }),
+    );
+    let hints = hints["result"].as_array().unwrap();
+    assert_eq!(hints.len(), 1);
+    assert_eq!(hints[0]["position"], json!({ "line": 1, "character": 3 }));
+    assert_eq!(hints[0]["label"], "Bob, 10 Jun 2025 · shout");
+
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": document, "position": { "line": 0, "character": 1 } }),
+    );
+    let markdown = hover["result"]["contents"]["value"].as_str().unwrap();
+    assert!(markdown.contains("Alice <alice@example.com> · 09 Jun 2025"));
+    assert!(markdown.ends_with("add lib\n\nDetails"));
+
+    // A saved but uncommitted edit is shown as such, and the client is asked to refresh
+    std::fs::write(&path, "ONE\nTWO\n").unwrap();
+    client.notify("textDocument/didSave", json!({ "textDocument": document }));
+    let refresh = client.receive();
+    assert_eq!(refresh["method"], "workspace/codeLens/refresh");
+    client.send(json!({ "jsonrpc": "2.0", "id": refresh["id"], "result": null }));
+
+    let lenses = client.request("textDocument/codeLens", json!({ "textDocument": document }));
+    assert_eq!(lenses["result"][0]["command"]["title"], "Not committed yet");
+
+    let unknown = client.request("textDocument/definition", json!({}));
+    assert_eq!(unknown["error"]["code"], -32601);
+
+    let shutdown = client.request("shutdown", Value::Null);
+    assert_eq!(shutdown["result"], Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::commit_files;
+use git2::Repository;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+
+/// A `wer serve` process on a free port, stopped when dropped
+struct Server {
+    child: Child,
+    address: String,
+}
+
+impl Server {
+    fn start(directory: &std::path::Path) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_wer"))
+            .args(["serve", "--port", "0"])
+            .current_dir(directory)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        // "Serving <repo> at http://127.0.0.1:PORT/"
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let address = line
+            .trim_end()
+            .rsplit_once("http://")
+            .map(|(_, url)| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| panic!("unexpected startup line: {}", line));
+
+        Server { child, address }
+    }
+
+    /// GET a path, returning the status line and the body
+    fn get(&self, path: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            path, self.address
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn serve_browses_tree_blame_and_api() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    commit_files(
+        &repo,
+        &[("src/main.rs", "fn main() {}\n")],
+        "Alice",
+        1749456964,
+        "init",
+    );
+    commit_files(
+        &repo,
+        &[("README.md", "# Demo\n")],
+        "Bob",
+        1749556964,
+        "add readme",
+    );
+
+    let server = Server::start(dir.path());
+    assert!(server.address.starts_with("127.0.0.1:"));
+
+    let (status, page) = server.get("/");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(page.contains("<a href=\"/tree/src\">src/</a>"));
+    assert!(page.contains("<a href=\"/blame/README.md\">README.md</a>"));
+
+    let (status, page) = server.get("/blame/src/main.rs");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(page.contains("Blame: src/main.rs"));
+
+    let (status, json) = server.get("/api/tree");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let entries: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(entries[0]["name"], "src");
+    assert_eq!(entries[0]["kind"], "tree");
+    assert_eq!(entries[0]["last_commit"]["summary"], "init");
+    assert_eq!(entries[1]["last_commit"]["author"], "Bob");
+
+    let (status, json) = server.get("/api/blame/src/main.rs");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let lines: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(lines[0]["author"], "Alice");
+
+    let (status, _) = server.get("/api/blame/nope.rs");
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+}